@@ -0,0 +1,2 @@
+DROP TABLE dice_settings;
+DROP TYPE game_system;
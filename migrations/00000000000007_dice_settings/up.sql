@@ -0,0 +1,6 @@
+CREATE TYPE game_system AS ENUM ('gurps', 'd20', 'd100', 'pbta');
+
+CREATE TABLE dice_settings (
+	id      BIGINT PRIMARY KEY,
+	system  game_system
+);
@@ -0,0 +1,8 @@
+-- An enum value can't be dropped, so recreate the type without it, first
+-- clearing any settings that use it.
+UPDATE dice_settings SET system = NULL WHERE system = 'fate';
+
+ALTER TYPE game_system RENAME TO game_system_old;
+CREATE TYPE game_system AS ENUM ('gurps', 'd20', 'd100', 'pbta');
+ALTER TABLE dice_settings ALTER COLUMN system TYPE game_system USING system::text::game_system;
+DROP TYPE game_system_old;
@@ -0,0 +1,7 @@
+-- Recreate the type rather than using ALTER TYPE ... ADD VALUE, which
+-- PostgreSQL before 12 refuses to run inside the transaction that wraps
+-- each migration.
+ALTER TYPE game_system RENAME TO game_system_old;
+CREATE TYPE game_system AS ENUM ('gurps', 'd20', 'd100', 'pbta', 'fate');
+ALTER TABLE dice_settings ALTER COLUMN system TYPE game_system USING system::text::game_system;
+DROP TYPE game_system_old;
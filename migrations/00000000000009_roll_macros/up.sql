@@ -0,0 +1,9 @@
+CREATE TABLE macros (
+	name      TEXT NOT NULL,
+	owner   BIGINT NOT NULL,
+	channel BIGINT NOT NULL,
+	shared   BOOLEAN NOT NULL DEFAULT FALSE,
+	body      TEXT NOT NULL,
+
+	PRIMARY KEY (name, owner)
+);
@@ -0,0 +1,15 @@
+CREATE TABLE rolls (
+	id        BIGSERIAL PRIMARY KEY,
+	channel      BIGINT NOT NULL,
+	roller       BIGINT NOT NULL,
+	message      BIGINT NOT NULL,
+	expression     TEXT NOT NULL,
+	result         TEXT NOT NULL,
+	sides         INT[] NOT NULL,
+	faces         INT[] NOT NULL,
+	total        BIGINT NOT NULL,
+	timestamp TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX rolls_channel ON rolls (channel, timestamp);
+CREATE INDEX rolls_roller ON rolls (roller, timestamp);
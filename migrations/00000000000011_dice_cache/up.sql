@@ -0,0 +1,7 @@
+CREATE TABLE dice_cache (
+	message      BIGINT PRIMARY KEY,
+	input          TEXT NOT NULL,
+	timestamp TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX dice_cache_timestamp ON dice_cache (timestamp);
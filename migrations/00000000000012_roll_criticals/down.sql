@@ -0,0 +1,3 @@
+ALTER TABLE rolls DROP COLUMN critical;
+
+DROP TYPE critical;
@@ -0,0 +1,3 @@
+CREATE TYPE critical AS ENUM ('success', 'failure');
+
+ALTER TABLE rolls ADD COLUMN critical critical;
@@ -0,0 +1 @@
+ALTER TABLE dice_settings DROP COLUMN embed;
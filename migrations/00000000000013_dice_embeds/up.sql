@@ -0,0 +1 @@
+ALTER TABLE dice_settings ADD COLUMN embed BOOLEAN;
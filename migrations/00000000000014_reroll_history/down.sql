@@ -0,0 +1,5 @@
+ALTER TABLE dice_settings DROP COLUMN own_rerolls;
+
+ALTER TABLE dice_cache
+	DROP COLUMN roller,
+	DROP COLUMN rerolls;
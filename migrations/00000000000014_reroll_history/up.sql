@@ -0,0 +1,8 @@
+-- Cached rolls from before rerollers were tracked can't be attributed.
+DELETE FROM dice_cache;
+
+ALTER TABLE dice_cache
+	ADD COLUMN roller  BIGINT NOT NULL,
+	ADD COLUMN rerolls INTEGER NOT NULL;
+
+ALTER TABLE dice_settings ADD COLUMN own_rerolls BOOLEAN;
@@ -0,0 +1,4 @@
+DROP INDEX rolls_seed;
+
+ALTER TABLE rolls DROP COLUMN input;
+ALTER TABLE rolls DROP COLUMN seed;
@@ -0,0 +1,4 @@
+ALTER TABLE rolls ADD COLUMN seed BIGINT;
+ALTER TABLE rolls ADD COLUMN input TEXT;
+
+CREATE INDEX rolls_seed ON rolls (channel, seed);
@@ -4,6 +4,7 @@ pub mod model;
 
 use crate::db::model::*;
 use crate::db::schema::*;
+use chrono::{DateTime, Utc};
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
@@ -52,6 +53,168 @@ impl DynamicPrefix {
     }
 }
 
+pub struct DiceConfig;
+impl DiceConfig {
+    pub fn get() -> QueryResult<HashMap<i64, DiceSettings>> {
+        Ok(dice_settings::table
+            .load::<DiceSettings>(&DB.get())?
+            .into_iter()
+            .map(|s| (s.id, s))
+            .collect())
+    }
+
+    pub fn set(settings: &DiceSettings) -> QueryResult<DiceSettings> {
+        Ok(diesel::insert_into(dice_settings::table)
+            .values(settings)
+            .on_conflict(dice_settings::id)
+            .do_update()
+            .set(settings)
+            .get_result(&DB.get())?)
+    }
+}
+
+pub struct DiceCache;
+impl DiceCache {
+    pub fn add(roll: &CachedRoll) -> QueryResult<CachedRoll> {
+        Ok(diesel::insert_into(dice_cache::table)
+            .values(roll)
+            .on_conflict(dice_cache::message)
+            .do_update()
+            .set(roll)
+            .get_result(&DB.get())?)
+    }
+
+    /// Find a roll in the cache, if it was cached since the given time.
+    pub fn get(message: i64, since: DateTime<Utc>) -> QueryResult<CachedRoll> {
+        Ok(dice_cache::table
+            .filter(dice_cache::message.eq(message))
+            .filter(dice_cache::timestamp.ge(since))
+            .first(&DB.get())?)
+    }
+
+    /// Evict rolls cached before the given time, and any older than the newest `keep`.
+    pub fn evict(before: DateTime<Utc>, keep: i64) -> QueryResult<usize> {
+        let db = DB.get();
+
+        // The oldest roll to keep, found by walking the timestamp index.
+        let oldest = dice_cache::table
+            .select(dice_cache::timestamp)
+            .order_by(dice_cache::timestamp.desc())
+            .offset(keep.max(1) - 1)
+            .first::<DateTime<Utc>>(&db)
+            .optional()?;
+
+        let cutoff = oldest.map_or(before, |oldest| oldest.max(before));
+
+        Ok(diesel::delete(dice_cache::table.filter(dice_cache::timestamp.lt(cutoff))).execute(&db)?)
+    }
+}
+
+pub struct Macros;
+impl Macros {
+    pub fn get(name: &str, owner: i64) -> QueryResult<Macro> {
+        Ok(macros::table.find((name, owner)).first(&DB.get())?)
+    }
+
+    /// Find a macro by name, preferring the user's own to one shared with the channel.
+    pub fn find(name: &str, owner: i64, channel: i64) -> QueryResult<Macro> {
+        let mut found = macros::table
+            .filter(macros::name.eq(name))
+            .filter(macros::owner.eq(owner).or(macros::channel.eq(channel).and(macros::shared)))
+            .get_results::<Macro>(&DB.get())?;
+
+        found.sort_by_key(|m| m.owner != owner);
+        found.into_iter().next().ok_or(QueryError::NotFound)
+    }
+
+    /// List the user's own macros, and those shared with the channel.
+    pub fn list(owner: i64, channel: i64) -> QueryResult<Vec<Macro>> {
+        Ok(macros::table
+            .filter(macros::owner.eq(owner).or(macros::channel.eq(channel).and(macros::shared)))
+            .order_by(macros::name)
+            .get_results(&DB.get())?)
+    }
+
+    pub fn set(m: &Macro) -> QueryResult<Macro> {
+        Ok(diesel::insert_into(macros::table)
+            .values(m)
+            .on_conflict((macros::name, macros::owner))
+            .do_update()
+            .set(m)
+            .get_result(&DB.get())?)
+    }
+
+    pub fn del(m: &Macro) -> QueryResult<Macro> {
+        Ok(diesel::delete(m).get_result(&DB.get())?)
+    }
+}
+
+pub struct RollLog;
+impl RollLog {
+    pub fn add(entries: &[NewRollEntry]) -> QueryResult<usize> {
+        Ok(diesel::insert_into(rolls::table)
+            .values(entries)
+            .execute(&DB.get())?)
+    }
+
+    /// Find a page of the most recent rolls in a channel, optionally by a single user
+    /// or with an expression matching a partial string.
+    pub fn find(channel: i64, roller: Option<i64>, partial: Option<&str>, page: i64, per_page: i64)
+        -> QueryResult<Vec<RollEntry>>
+    {
+        let mut query = rolls::table
+            .filter(rolls::channel.eq(channel))
+            .into_boxed();
+
+        if let Some(roller) = roller {
+            query = query.filter(rolls::roller.eq(roller));
+        }
+
+        if let Some(partial) = partial {
+            query = query.filter(rolls::expression.ilike(format!("%{}%", escape_like(partial))));
+        }
+
+        Ok(query
+            .order_by(rolls::timestamp.desc())
+            .offset(page.saturating_mul(per_page))
+            .limit(per_page)
+            .get_results(&DB.get())?)
+    }
+
+    /// The latest roll in a channel made from the given seed.
+    pub fn by_seed(channel: i64, seed: i64) -> QueryResult<RollEntry> {
+        Ok(rolls::table
+            .filter(rolls::channel.eq(channel))
+            .filter(rolls::seed.eq(seed))
+            .order_by(rolls::timestamp.desc())
+            .first(&DB.get())?)
+    }
+
+    /// The latest `limit` rolls in a channel, optionally by a single user, oldest first.
+    pub fn history(channel: i64, roller: Option<i64>, limit: i64) -> QueryResult<Vec<RollEntry>> {
+        let mut query = rolls::table
+            .filter(rolls::channel.eq(channel))
+            .into_boxed();
+
+        if let Some(roller) = roller {
+            query = query.filter(rolls::roller.eq(roller));
+        }
+
+        let mut entries = query
+            .order_by(rolls::timestamp.desc())
+            .limit(limit)
+            .get_results::<RollEntry>(&DB.get())?;
+
+        entries.reverse();
+        Ok(entries)
+    }
+}
+
+/// Escape the wildcards in text matched by `LIKE`, which uses backslash as its escape character.
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
 pub struct Memory;
 impl Memory {
     pub fn get_keyword(kw: &str) -> QueryResult<Keyword> {
@@ -1,4 +1,5 @@
 use crate::db::schema::*;
+use crate::ext::dice::{Critical, GameSystem};
 
 use chrono::{DateTime, Utc};
 use diesel::{AsChangeset, Associations, Identifiable, Insertable, Queryable};
@@ -76,6 +77,137 @@ pub struct Channel {
 }
 
 
+#[derive(Clone, Copy, Debug, PartialEq, DbEnum)]
+#[PgType = "game_system"]
+#[DieselType = "GameSystemMapping"]
+pub enum DbGameSystem {
+    Gurps,
+    D20,
+    D100,
+    Pbta,
+    Fate,
+}
+
+impl From<GameSystem> for DbGameSystem {
+    fn from(system: GameSystem) -> Self {
+        match system {
+            GameSystem::Gurps => DbGameSystem::Gurps,
+            GameSystem::D20 => DbGameSystem::D20,
+            GameSystem::D100 => DbGameSystem::D100,
+            GameSystem::Pbta => DbGameSystem::Pbta,
+            GameSystem::Fate => DbGameSystem::Fate,
+        }
+    }
+}
+
+impl From<DbGameSystem> for GameSystem {
+    fn from(system: DbGameSystem) -> Self {
+        match system {
+            DbGameSystem::Gurps => GameSystem::Gurps,
+            DbGameSystem::D20 => GameSystem::D20,
+            DbGameSystem::D100 => GameSystem::D100,
+            DbGameSystem::Pbta => GameSystem::Pbta,
+            DbGameSystem::Fate => GameSystem::Fate,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, DbEnum)]
+#[PgType = "critical"]
+#[DieselType = "CriticalMapping"]
+pub enum DbCritical {
+    Success,
+    Failure,
+}
+
+impl From<Critical> for DbCritical {
+    fn from(critical: Critical) -> Self {
+        match critical {
+            Critical::Success => DbCritical::Success,
+            Critical::Failure => DbCritical::Failure,
+        }
+    }
+}
+
+impl From<DbCritical> for Critical {
+    fn from(critical: DbCritical) -> Self {
+        match critical {
+            DbCritical::Success => Critical::Success,
+            DbCritical::Failure => Critical::Failure,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, AsChangeset, Identifiable, Insertable, Queryable)]
+#[table_name = "dice_settings"]
+#[primary_key(id)]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct DiceSettings {
+    pub id: i64,
+    pub system: Option<DbGameSystem>,
+    pub embed: Option<bool>,
+    pub own_rerolls: Option<bool>,
+    pub inline: Option<bool>,
+}
+
+#[derive(Clone, Debug, AsChangeset, Identifiable, Insertable, Queryable)]
+#[table_name = "dice_cache"]
+#[primary_key(message)]
+pub struct CachedRoll {
+    pub message: i64,
+    pub input: String,
+    pub timestamp: DateTime<Utc>,
+    pub roller: i64,
+    pub rerolls: i32,
+}
+
+#[derive(Clone, Debug, AsChangeset, Identifiable, Insertable, Queryable)]
+#[table_name = "macros"]
+#[primary_key(name, owner)]
+pub struct Macro {
+    pub name: String,
+    pub owner: i64,
+    pub channel: i64,
+    pub shared: bool,
+    pub body: String,
+}
+
+#[derive(Clone, Debug, Identifiable, Queryable)]
+#[table_name = "rolls"]
+#[primary_key(id)]
+pub struct RollEntry {
+    pub id: i64,
+    pub channel: i64,
+    pub roller: i64,
+    pub message: i64,
+    pub expression: String,
+    pub result: String,
+    pub sides: Vec<i32>,
+    pub faces: Vec<i32>,
+    pub total: i64,
+    pub timestamp: DateTime<Utc>,
+    pub critical: Option<DbCritical>,
+    pub seed: Option<i64>,
+    pub input: Option<String>,
+}
+
+#[derive(Clone, Debug, Insertable)]
+#[table_name = "rolls"]
+pub struct NewRollEntry {
+    pub channel: i64,
+    pub roller: i64,
+    pub message: i64,
+    pub expression: String,
+    pub result: String,
+    pub sides: Vec<i32>,
+    pub faces: Vec<i32>,
+    pub total: i64,
+    pub timestamp: DateTime<Utc>,
+    pub critical: Option<DbCritical>,
+    pub seed: Option<i64>,
+    pub input: Option<String>,
+}
+
 #[derive(Clone, Debug, DbEnum)]
 pub enum ActivityKind {
     Playing,
@@ -41,6 +41,26 @@ table! {
     }
 }
 
+table! {
+    dice_cache (message) {
+        message -> Int8,
+        input -> Text,
+        timestamp -> Timestamptz,
+        roller -> Int8,
+        rerolls -> Int4,
+    }
+}
+
+table! {
+    dice_settings (id) {
+        id -> Int8,
+        system -> Nullable<crate::db::model::GameSystemMapping>,
+        embed -> Nullable<Bool>,
+        own_rerolls -> Nullable<Bool>,
+        inline -> Nullable<Bool>,
+    }
+}
+
 table! {
     keywords (keyword) {
         keyword -> Text,
@@ -52,6 +72,34 @@ table! {
     }
 }
 
+table! {
+    macros (name, owner) {
+        name -> Text,
+        owner -> Int8,
+        channel -> Int8,
+        shared -> Bool,
+        body -> Text,
+    }
+}
+
+table! {
+    rolls (id) {
+        id -> Int8,
+        channel -> Int8,
+        roller -> Int8,
+        message -> Int8,
+        expression -> Text,
+        result -> Text,
+        sides -> Array<Int4>,
+        faces -> Array<Int4>,
+        total -> Int8,
+        timestamp -> Timestamptz,
+        critical -> Nullable<crate::db::model::CriticalMapping>,
+        seed -> Nullable<Int8>,
+        input -> Nullable<Text>,
+    }
+}
+
 table! {
     notes (name, pin) {
         pin -> Int8,
@@ -77,7 +125,11 @@ allow_tables_to_appear_in_same_query!(
     channels,
     characters,
     definitions,
+    dice_cache,
+    dice_settings,
     keywords,
+    macros,
     notes,
     prefixes,
+    rolls,
 );
@@ -1,210 +1,1795 @@
 use lazy_static::lazy_static;
 use rand::distributions::{Distribution, Uniform};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use std::fmt;
+use std::iter::Peekable;
+use std::ops::Range;
 use std::str::FromStr;
+use std::vec::IntoIter;
 use std::{error::Error, num::ParseIntError};
 
+/// The most extra dice a single die may add by exploding.
+const EXPLODE_LIMIT: usize = 100;
+
+/// The most times a single die may be rerolled.
+const REROLL_LIMIT: usize = 100;
+
+/// The most pairs of outcomes a distribution may combine.
+const ODDS_LIMIT: usize = 10_000_000;
+
+/// Exploding chains less likely than this are dropped from a distribution.
+const ODDS_EPSILON: f64 = 1e-12;
+
+/// The probability of each outcome of an expression.
+type Dist = BTreeMap<isize, f64>;
+
 #[derive(Clone, Debug)]
 pub struct DiceRoll { rolls: Vec<Roll> }
 
-impl DiceRoll {
-    fn new(mut t: Vec<Term>) -> Self {
-        let repeat;
-        let versus;
+impl DiceRoll {
+    pub fn rolls(&self) -> &[Roll] {
+        &self.rolls
+    }
+
+    /// Resolve any rolls against a target according to the given game system.
+    pub fn with_system(mut self, system: GameSystem) -> Self {
+        for roll in &mut self.rolls {
+            roll.system = system;
+        }
+
+        self
+    }
+
+    /// The results as text, or `None` as soon as they run past `max` bytes.
+    pub fn render(&self, max: usize) -> Option<String> {
+        let mut out = Bounded { text: String::new(), max };
+        fmt::write(&mut out, format_args!("{}", self)).ok().map(|_| out.text)
+    }
+}
+
+/// A string that refuses any write that would take it past `max` bytes.
+struct Bounded {
+    text: String,
+    max: usize,
+}
+
+impl fmt::Write for Bounded {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.text.len() + s.len() > self.max {
+            return Err(fmt::Error);
+        }
+
+        self.text.push_str(s);
+        Ok(())
+    }
+}
+
+/// An RNG for rolls that can be reproduced exactly from their seed.
+pub fn seeded(seed: u64) -> ChaCha20Rng {
+    ChaCha20Rng::seed_from_u64(seed)
+}
+
+/// Caps on the size of a roll, so that no expression can tie up the bot.
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    /// The most dice a single term may roll.
+    pub dice: usize,
+    /// The most dice a whole roll may roll, counting every explosion and reroll.
+    pub rolled: usize,
+    /// The most sides a single die may have.
+    pub sides: isize,
+    /// The most times an expression may be repeated.
+    pub repeat: usize,
+    /// The longest a message of results may be, in bytes.
+    pub output: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits { dice: 1000, rolled: 10_000, sides: 1_000_000, repeat: 20, output: 1800 }
+    }
+}
+
+impl Limits {
+    /// Read limits from `DICE_MAX_DICE`, `DICE_MAX_ROLLED`, `DICE_MAX_SIDES`,
+    /// `DICE_MAX_REPEAT` and `DICE_MAX_OUTPUT`, using the defaults for any that
+    /// are unset or invalid.
+    pub fn from_env() -> Self {
+        fn var<T: FromStr>(key: &str, default: T) -> T {
+            std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        }
+
+        let default = Limits::default();
+
+        Limits {
+            dice: var("DICE_MAX_DICE", default.dice),
+            rolled: var("DICE_MAX_ROLLED", default.rolled),
+            sides: var("DICE_MAX_SIDES", default.sides),
+            repeat: var("DICE_MAX_REPEAT", default.repeat),
+            output: var("DICE_MAX_OUTPUT", default.output),
+        }
+    }
+
+    fn dice(&self, count: isize) -> Result<usize, RollError> {
+        match count {
+            c if c > self.dice as isize => Err(RollError::TooManyDice(self.dice)),
+            c => Ok(c.max(0) as usize),
+        }
+    }
+
+    fn sides(&self, sides: isize) -> Result<(), RollError> {
+        if sides > self.sides {
+            Err(RollError::TooManySides(self.sides))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Count one more die against the dice rolled so far.
+    fn spend(&self, rolled: &mut usize) -> Result<(), RollError> {
+        if *rolled >= self.rolled {
+            Err(RollError::TooManyRolled(self.rolled))
+        } else {
+            *rolled += 1;
+            Ok(())
+        }
+    }
+}
+
+/// A parsed dice expression, which can be rolled any number of times.
+#[derive(Clone, Debug)]
+pub struct DiceExpr {
+    expr: Expr,
+    repeat: usize,
+    versus: Option<(Expr, Option<String>)>,
+}
+
+impl DiceExpr {
+    pub fn roll<R: Rng>(&self, rng: &mut R, limits: &Limits) -> Result<DiceRoll, RollError> {
+        if self.repeat > limits.repeat {
+            return Err(RollError::TooManyRepeats(limits.repeat));
+        }
+
+        let mut rolls = Vec::with_capacity(self.repeat);
+        let mut rolled = 0;
+
+        for _ in 1..=self.repeat {
+            let (expr, total) = self.expr.eval(rng, limits, &mut rolled)?;
+            let versus = match &self.versus {
+                Some((target, tag)) => Some((target.eval(rng, limits, &mut rolled)?.1, tag.clone())),
+                None => None,
+            };
+
+            rolls.push(Roll { expr, total, versus, system: GameSystem::default() });
+        }
+
+        Ok(DiceRoll { rolls })
+    }
+
+    /// The exact distribution of the expression's outcomes, and its chance of success
+    /// against any target under the given game system.
+    ///
+    /// Fails if the expression is too large to work out, or keeps the best or worst of
+    /// dice that explode into extra dice.
+    pub fn odds(&self, system: GameSystem, limits: &Limits) -> Result<Odds, RollError> {
+        let dist = self.expr.odds(limits)?;
+        let pool = self.expr.is_pool();
+
+        let success = match &self.versus {
+            Some((target, _)) => {
+                let mut chance = 0.0;
+
+                for (&target, &p) in &target.odds(limits)? {
+                    for (&total, &q) in &dist {
+                        if (pool && total >= target) || (!pool && system.succeeds(total, target)) {
+                            chance += p * q;
+                        }
+                    }
+                }
+
+                Some(chance)
+            }
+            None => None,
+        };
+
+        Ok(Odds { dist, success })
+    }
+}
+
+impl fmt::Display for DiceExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expr)?;
+
+        match &self.versus {
+            Some((target, Some(tag))) => write!(f, " vs {} {}", tag, target),
+            Some((target, None)) => write!(f, " vs {}", target),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The exact distribution of a dice expression's outcomes.
+#[derive(Clone, Debug)]
+pub struct Odds {
+    dist: Dist,
+    success: Option<f64>,
+}
+
+impl Odds {
+    pub fn mean(&self) -> f64 {
+        self.dist.iter().map(|(&v, &p)| v as f64 * p).sum()
+    }
+
+    pub fn stddev(&self) -> f64 {
+        let mean = self.mean();
+        self.dist.iter().map(|(&v, &p)| (v as f64 - mean).powi(2) * p).sum::<f64>().sqrt()
+    }
+
+    /// The chance of succeeding against the expression's target, if it has one.
+    pub fn success(&self) -> Option<f64> {
+        self.success
+    }
+
+    /// The chance of each outcome, grouped into ranges if there are too many to list.
+    ///
+    /// Outcomes too unlikely to show are left out, e.g. the long tails of exploding dice.
+    fn histogram(&self) -> Vec<(isize, isize, f64)> {
+        const ROWS: isize = 20;
+        const SHOWN: f64 = 0.000_05;
+
+        let mut shown = self.dist.iter().filter(|&(_, &p)| p >= SHOWN).map(|(&v, _)| v);
+        let (low, high) = match (shown.next(), shown.next_back()) {
+            (Some(low), Some(high)) => (low, high),
+            (Some(low), None) => (low, low),
+            _ => return Vec::new(),
+        };
+
+        let width = (high - low) / ROWS + 1;
+        let mut rows: Vec<(isize, isize, f64)> = Vec::new();
+
+        for (&v, &p) in self.dist.range(low..=high) {
+            let start = low + (v - low) / width * width;
+
+            match rows.last_mut() {
+                Some(row) if row.0 == start => row.2 += p,
+                _ => rows.push((start, (start + width - 1).min(high), p)),
+            }
+        }
+
+        rows
+    }
+}
+
+impl fmt::Display for Odds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Mean: {:.2}, SD: {:.2}", self.mean(), self.stddev())?;
+
+        if let Some(success) = self.success {
+            write!(f, ", Success: {:.2}%", success * 100.0)?;
+        }
+
+        let rows = self.histogram();
+        let max = rows.iter().map(|r| r.2).fold(0.0, f64::max);
+        let label = |low: isize, high: isize| {
+            if low == high { low.to_string() } else { format!("{}–{}", low, high) }
+        };
+        let pad = rows.iter().map(|&(low, high, _)| label(low, high).chars().count()).max().unwrap_or(0);
+
+        for (low, high, p) in rows {
+            let label = label(low, high);
+            let bar = (p / max * 30.0).round() as usize;
+            let pad = pad - label.chars().count();
+
+            write!(f, "\n{}{} {:>6.2}% {}", " ".repeat(pad), label, p * 100.0, "█".repeat(bar))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Combine two independent distributions outcome by outcome.
+fn combine<F: Fn(isize, isize) -> Result<isize, RollError>>(lhs: &Dist, rhs: &Dist, f: F) -> Result<Dist, RollError> {
+    if lhs.len().saturating_mul(rhs.len()) > ODDS_LIMIT {
+        return Err(RollError::TooComplex);
+    }
+
+    let mut dist = Dist::new();
+
+    for (&l, &p) in lhs {
+        for (&r, &q) in rhs {
+            *dist.entry(f(l, r)?).or_insert(0.0) += p * q;
+        }
+    }
+
+    Ok(dist)
+}
+
+/// Sum `count` independent rolls of a distribution.
+fn repeat(count: usize, die: &Dist) -> Result<Dist, RollError> {
+    let mut work = 0usize;
+
+    (0..count).try_fold(std::iter::once((0, 1.0)).collect(), |dist: Dist, _| {
+        work = work.saturating_add(dist.len().saturating_mul(die.len()));
+
+        if work > ODDS_LIMIT {
+            Err(RollError::TooComplex)
+        } else {
+            combine(&dist, die, |a, b| a.checked_add(b).ok_or(RollError::Overflow))
+        }
+    })
+}
+
+impl fmt::Display for DiceRoll {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut iter = self.rolls.iter();
+        let first = match iter.next() {
+            Some(first) => first,
+            None => return Ok(()),
+        };
+
+        write!(f, "{}", first)?;
+
+        for roll in iter {
+            write!(f, "\n{}", roll)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for DiceExpr {
+    type Err = ParseRollError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        lazy_static! {
+            static ref RE: Regex = Regex::new(r"(?xi)
+                (?: \d* d \( | \d+ d (?:\d+|f)? | d (?:\d+|f) )  # Term::Dice
+                (?: [bw] \d+ | [kd] [hl] \d+ | (?:min|max) \d+      # Modifiers, where a reroll
+                  | \s* (?:adv|dis) | ! [!p]? (?: (?:[<>]=?|=)? \d+ )?
+                  | r o? (?:[<>]=?|=)? \d+                             # must follow without spaces.
+                  | (?:[<>]=?|=) \d+ | f (?:[<>]=?|=)? \d+ )*        |
+                [-+×x*/\\÷%^()]                                     | # Term::<Op>
+                v(?:s|ersus)? \s+ (?: [[:alpha:]] [^\d()+*/^%]* )?  | # Term::Versus
+                r(?:e|epeat)? \s* \d+                               | # Term::Repeat
+                \d+                                                   # Term::Num")
+            .unwrap();
+        }
+
+        let mut tokens = Vec::new();
+        let mut last = 0;
+
+        for m in RE.find_iter(s) {
+            unrecognised(s, last..m.start())?;
+            last = m.end();
+
+            let term = |span: Range<usize>| {
+                Term::from_str(&s[span.clone()]).map_err(|err| err.at(span, s))
+            };
+
+            // A die with a parenthesised number of sides, e.g. `2d(1d4)`.
+            if m.as_str().len() > 1 && m.as_str().ends_with('(') {
+                tokens.push((term(m.start()..m.end() - 1)?, m.start()..m.end() - 1));
+                tokens.push((Term::Open, m.end() - 1..m.end()));
+            } else {
+                tokens.push((term(m.start()..m.end())?, m.start()..m.end()));
+            }
+        }
+
+        unrecognised(s, last..s.len())?;
+
+        let repeat = match tokens.iter().find(|(t, _)| t.is_repeat()) {
+            Some((Term::Repeat(0), span)) => return Err(ParseRollError::new(ParseErrorKind::NoRepeats, span.clone(), s)),
+            Some((Term::Repeat(i), _)) => *i,
+            _ => 1,
+        };
+
+        tokens.retain(|(t, _)| !t.is_repeat());
+
+        // Without any dice, the expression modifies a default roll of 3d6.
+        let end = tokens.iter().position(|(t, _)| t.is_versus()).unwrap_or(tokens.len());
+        if !tokens[..end].iter().any(|(t, _)| t.is_dice()) {
+            if end > 0 && !tokens[0].0.is_operator() {
+                tokens.insert(0, (Term::Op(Op::Add), 0..0));
+            }
+
+            tokens.insert(0, (Term::Dice { n: Some(3), s: Some(6), fate: false, mods: Vec::new() }, 0..0));
+        }
+
+        let mut parser = Parser { input: s, tokens: tokens.into_iter().peekable(), depth: 0 };
+        let expr = parser.expr()?;
+
+        let versus = match parser.tokens.next() {
+            Some((Term::Versus(tag), _)) => Some((parser.expr()?, tag)),
+            Some((_, span)) => return Err(ParseRollError::new(ParseErrorKind::Unexpected, span, s)),
+            None => None,
+        };
+
+        if let Some((_, span)) = parser.tokens.next() {
+            return Err(ParseRollError::new(ParseErrorKind::Unexpected, span, s));
+        }
+
+        Ok(DiceExpr { expr, repeat, versus })
+    }
+}
+
+/// Fail on anything but whitespace in the given span of the input.
+fn unrecognised(s: &str, span: Range<usize>) -> Result<(), ParseRollError> {
+    let gap = &s[span.clone()];
+
+    if gap.trim().is_empty() {
+        Ok(())
+    } else {
+        let start = span.start + gap.len() - gap.trim_start().len();
+        let end = span.start + gap.trim_end().len();
+        Err(ParseRollError::new(ParseErrorKind::Unrecognised, start..end, s))
+    }
+}
+
+/// A recursive descent parser building an `Expr` tree from a stream of `Term` tokens.
+///
+/// In order of increasing precedence: `+ -`, `× / %`, unary `-`, `^` (right associative),
+/// and `d`, which takes any preceding primary expression as its number of dice.
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Peekable<IntoIter<(Term, Range<usize>)>>,
+    /// How many unary expressions are being parsed, which every nested expression passes through.
+    depth: usize,
+}
+
+/// The deepest an expression may nest before parsing gives up.
+const MAX_DEPTH: usize = 64;
+
+impl<'a> Parser<'a> {
+    fn eat(&mut self, ops: &[Op]) -> Option<Op> {
+        match self.tokens.peek() {
+            Some((Term::Op(op), _)) if ops.contains(op) => {
+                let op = *op;
+                self.tokens.next();
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn error(&self, kind: ParseErrorKind, span: Option<Range<usize>>) -> ParseRollError {
+        let end = self.input.len();
+        ParseRollError::new(kind, span.unwrap_or(end..end), self.input)
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseRollError> {
+        let mut lhs = self.term()?;
+
+        while let Some(op) = self.eat(&[Op::Add, Op::Sub]) {
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseRollError> {
+        let mut lhs = self.unary()?;
+
+        while let Some(op) = self.eat(&[Op::Mul, Op::Div, Op::Rem]) {
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseRollError> {
+        if self.depth >= MAX_DEPTH {
+            let span = self.tokens.peek().map(|(_, span)| span.clone());
+            return Err(self.error(ParseErrorKind::TooDeep, span));
+        }
+
+        self.depth += 1;
+        let expr = match self.eat(&[Op::Add, Op::Sub]) {
+            Some(Op::Sub) => self.unary().map(|e| Expr::Neg(Box::new(e))),
+            Some(_) => self.unary(),
+            None => self.power(),
+        };
+        self.depth -= 1;
+
+        expr
+    }
+
+    fn power(&mut self) -> Result<Expr, ParseRollError> {
+        let base = self.dice()?;
+
+        if let Some(op) = self.eat(&[Op::Pow]) {
+            Ok(Expr::Binary(op, Box::new(base), Box::new(self.unary()?)))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn dice(&mut self) -> Result<Expr, ParseRollError> {
+        let mut expr = self.primary()?;
+
+        while let Some((Term::Dice { n: None, .. }, _)) = self.tokens.peek() {
+            if let Some((Term::Dice { s, fate, mods, .. }, _)) = self.tokens.next() {
+                expr = self.sides(expr, s, fate, mods)?;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn sides(&mut self, n: Expr, s: Option<isize>, fate: bool, mods: Vec<Modifier>) -> Result<Expr, ParseRollError> {
+        let s = match s {
+            _ if fate => Sides::Fate,
+            Some(s) => Sides::Num(Box::new(Expr::Num(s))),
+            None if self.tokens.peek().map(|(t, _)| t) == Some(&Term::Open) => {
+                Sides::Num(Box::new(self.primary()?))
+            }
+            None => Sides::Num(Box::new(Expr::Num(6))),
+        };
+
+        Ok(Expr::Dice { n: Box::new(n), s, mods, rolls: None })
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseRollError> {
+        match self.tokens.next() {
+            Some((Term::Num(i), _)) => Ok(Expr::Num(i)),
+            Some((Term::Dice { n, s, fate, mods }, _)) => {
+                let n = Expr::Num(n.unwrap_or(1) as isize);
+                self.sides(n, s, fate, mods)
+            }
+            Some((Term::Open, open)) => {
+                let expr = self.expr()?;
+                match self.tokens.next() {
+                    Some((Term::Close, _)) => Ok(Expr::Group(Box::new(expr))),
+                    Some((_, span)) => Err(self.error(ParseErrorKind::Unexpected, Some(span))),
+                    None => Err(self.error(ParseErrorKind::Unclosed, Some(open))),
+                }
+            }
+            Some((_, span)) => Err(self.error(ParseErrorKind::Unexpected, Some(span))),
+            None => Err(self.error(ParseErrorKind::Incomplete, None)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Num(isize),
+    Dice {
+        n: Box<Expr>,
+        s: Sides,
+        mods: Vec<Modifier>,
+        rolls: Option<Rolls>,
+    },
+    Neg(Box<Expr>),
+    Group(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Whether any dice in the expression match the predicate.
+    fn any_dice<F: Fn(&[Modifier], Option<&Rolls>) -> bool>(&self, f: &F) -> bool {
+        match self {
+            Expr::Num(_) => false,
+            Expr::Dice { n, s: Sides::Num(s), mods, rolls } => {
+                f(mods, rolls.as_ref()) || n.any_dice(f) || s.any_dice(f)
+            }
+            Expr::Dice { n, s: Sides::Fate, mods, rolls } => f(mods, rolls.as_ref()) || n.any_dice(f),
+            Expr::Neg(e) | Expr::Group(e) => e.any_dice(f),
+            Expr::Binary(_, lhs, rhs) => lhs.any_dice(f) || rhs.any_dice(f),
+        }
+    }
+
+    /// Whether the expression counts successes rather than summing its dice.
+    fn is_pool(&self) -> bool {
+        self.any_dice(&|mods, _| Rolls::pool(mods).is_some())
+    }
+
+    /// Whether any success-counting pool in the rolled expression botched.
+    fn is_botch(&self) -> bool {
+        self.any_dice(&|mods, rolls| rolls.map_or(false, |r| r.is_botch(mods)))
+    }
+
+    /// Collect every face rolled in the expression, as it came off the die.
+    fn faces(&self, faces: &mut Vec<Face>) {
+        match self {
+            Expr::Num(_) => (),
+            Expr::Dice { n, s, rolls, .. } => {
+                n.faces(faces);
+
+                if let Sides::Num(s) = s {
+                    s.faces(faces);
+                }
+
+                if let Some(rolls) = rolls {
+                    faces.extend(rolls.samples.iter().map(|&value| Face { sides: rolls.sides, value }));
+                }
+            }
+            Expr::Neg(e) | Expr::Group(e) => e.faces(faces),
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.faces(faces);
+                rhs.faces(faces);
+            }
+        }
+    }
+
+    /// The first die kept in the rolled expression.
+    fn natural(&self) -> Option<Face> {
+        match self {
+            Expr::Num(_) => None,
+            Expr::Dice { mods, rolls, .. } => rolls
+                .as_ref()
+                .and_then(|r| r.kept(mods).first().map(|&value| Face { sides: r.sides, value })),
+            Expr::Neg(e) | Expr::Group(e) => e.natural(),
+            Expr::Binary(_, lhs, rhs) => lhs.natural().or_else(|| rhs.natural()),
+        }
+    }
+
+    /// The exact distribution of the expression's value.
+    fn odds(&self, limits: &Limits) -> Result<Dist, RollError> {
+        match self {
+            Expr::Num(i) => Ok(std::iter::once((*i, 1.0)).collect()),
+            Expr::Dice { n, s, mods, .. } => {
+                let sides = match s {
+                    Sides::Num(s) => s
+                        .odds(limits)?
+                        .into_iter()
+                        .map(|(s, p)| if s < 1 { Err(RollError::NoSides) } else { Ok(((1, s), p)) })
+                        .collect::<Result<Vec<_>, _>>()?,
+                    Sides::Fate => vec![((-1, 1), 1.0)],
+                };
+
+                let mut dist = Dist::new();
+
+                for (count, p) in n.odds(limits)? {
+                    for &((low, high), q) in &sides {
+                        limits.sides(high)?;
+
+                        for (v, r) in Rolls::odds(limits.dice(Rolls::count(mods, count))?, low, high, mods)? {
+                            *dist.entry(v).or_insert(0.0) += p * q * r;
+                        }
+                    }
+                }
+
+                Ok(dist)
+            }
+            Expr::Neg(e) => e.odds(limits)?.into_iter().map(|(v, p)| Ok((v.checked_neg().ok_or(RollError::Overflow)?, p))).collect(),
+            Expr::Group(e) => e.odds(limits),
+            Expr::Binary(op, lhs, rhs) => combine(&lhs.odds(limits)?, &rhs.odds(limits)?, |l, r| op.apply(l, r)),
+        }
+    }
+
+    /// Roll every die in the expression, returning the rolled expression and its value.
+    fn eval<R: Rng>(&self, rng: &mut R, limits: &Limits, rolled: &mut usize) -> Result<(Expr, isize), RollError> {
+        Ok(match self {
+            Expr::Num(i) => (Expr::Num(*i), *i),
+            Expr::Dice { n, s, mods, .. } => {
+                let (n, count) = n.eval(rng, limits, rolled)?;
+                let (s, low, high) = match s {
+                    Sides::Num(s) => {
+                        let (s, sides) = s.eval(rng, limits, rolled)?;
+
+                        if sides < 1 {
+                            return Err(RollError::NoSides);
+                        }
+
+                        (Sides::Num(Box::new(s)), 1, sides)
+                    }
+                    Sides::Fate => (Sides::Fate, -1, 1),
+                };
+
+                let count = limits.dice(Rolls::count(mods, count))?;
+                limits.sides(high)?;
+
+                let rolls = Rolls::roll(rng, count, low, high, mods, limits, rolled)?;
+                let sum = rolls.sum(mods)?;
+
+                let expr = Expr::Dice { n: Box::new(n), s, mods: mods.clone(), rolls: Some(rolls) };
+                (expr, sum)
+            }
+            Expr::Neg(e) => {
+                let (e, value) = e.eval(rng, limits, rolled)?;
+                (Expr::Neg(Box::new(e)), value.checked_neg().ok_or(RollError::Overflow)?)
+            }
+            Expr::Group(e) => {
+                let (e, value) = e.eval(rng, limits, rolled)?;
+                (Expr::Group(Box::new(e)), value)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, l) = lhs.eval(rng, limits, rolled)?;
+                let (rhs, r) = rhs.eval(rng, limits, rolled)?;
+                (Expr::Binary(*op, Box::new(lhs), Box::new(rhs)), op.apply(l, r)?)
+            }
+        })
+    }
+}
+
+impl Expr {
+    /// Render the rolled expression in Discord markdown, striking through dropped dice.
+    fn markdown(&self) -> String {
+        match self {
+            Expr::Dice { n, s: Sides::Num(s), mods, rolls: Some(rolls) } => {
+                let modifiers = mods.iter().map(Modifier::to_string).collect::<String>();
+                format!("{}d{}{}{}", n.markdown(), s.markdown(), modifiers, rolls.markdown(mods))
+            }
+            Expr::Neg(e) => format!("-{}", e.markdown()),
+            Expr::Group(e) => format!("({})", e.markdown()),
+            Expr::Binary(op, lhs, rhs) => format!("{} {} {}", lhs.markdown(), op, rhs.markdown()),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Num(i) => write!(f, "{}", i),
+            Expr::Dice { n, s, mods, rolls } => {
+                write!(f, "{}d{}", n, s)?;
+
+                for m in mods {
+                    write!(f, "{}", m)?;
+                }
+
+                match (s, rolls) {
+                    (Sides::Fate, Some(rolls)) => {
+                        for die in &rolls.dice {
+                            match die.value {
+                                v if v > 0 => write!(f, "[+]")?,
+                                v if v < 0 => write!(f, "[-]")?,
+                                _ => write!(f, "[ ]")?,
+                            }
+                        }
+                    }
+                    (_, Some(rolls)) => write!(f, "{}", rolls)?,
+                    (_, None) => (),
+                }
+
+                Ok(())
+            }
+            Expr::Neg(e) => write!(f, "-{}", e),
+            Expr::Group(e) => write!(f, "({})", e),
+            Expr::Binary(op, lhs, rhs) => write!(f, "{} {} {}", lhs, op, rhs),
+        }
+    }
+}
+
+/// The faces of a die: either numbered from one, or Fudge/Fate dice.
+#[derive(Clone, Debug)]
+enum Sides {
+    Num(Box<Expr>),
+    Fate,
+}
+
+impl fmt::Display for Sides {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sides::Num(s) => write!(f, "{}", s),
+            Sides::Fate => write!(f, "F"),
+        }
+    }
+}
+
+/// A single die's result, and the number of sides it had (zero for Fate dice).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Face {
+    pub sides: isize,
+    pub value: isize,
+}
+
+#[derive(Clone, Debug)]
+pub struct Roll {
+    expr: Expr,
+    total: isize,
+    versus: Option<(isize, Option<String>)>,
+    system: GameSystem,
+}
+
+impl Roll {
+    pub fn total(&self) -> isize {
+        self.total
+    }
+
+    /// Every face rolled, in order, including any rerolled, dropped or modified.
+    pub fn faces(&self) -> Vec<Face> {
+        let mut faces = Vec::new();
+        self.expr.faces(&mut faces);
+        faces
+    }
+
+    /// Whether the roll was a critical success or failure, for rolls of the
+    /// game system's usual dice: 3d6 for GURPS, a d20 or a d100.
+    pub fn critical(&self) -> Option<Critical> {
+        if self.expr.is_pool() {
+            return if self.expr.is_botch() { Some(Critical::Failure) } else { None };
+        }
+
+        let faces = self.faces();
+
+        let usual = match (self.system, faces.as_slice()) {
+            (GameSystem::Gurps, f) => f.len() == 3 && f.iter().all(|f| f.sides == 6),
+            (GameSystem::D20, [first, ..]) => first.sides == 20,
+            (GameSystem::D100, [only]) => only.sides == 100,
+            _ => false,
+        };
+
+        if usual {
+            let target = self.versus.as_ref().map(|(target, _)| *target);
+            self.system.critical(self.total, target, self.natural())
+        } else {
+            None
+        }
+    }
+
+    /// The first die kept, if it's a d20 that could roll a natural 1 or 20.
+    fn natural(&self) -> Option<isize> {
+        self.expr.natural().filter(|f| f.sides == 20).map(|f| f.value)
+    }
+
+    /// How much the roll beat its target by, negative if it fell short. Roll-under
+    /// systems count down from the target.
+    pub fn margin(&self) -> Option<isize> {
+        let (target, _) = self.versus.as_ref()?;
+
+        match self.system {
+            _ if self.expr.is_pool() => Some(self.total - target),
+            GameSystem::Gurps | GameSystem::D100 => Some(target - self.total),
+            _ => Some(self.total - target),
+        }
+    }
+
+    /// How well the roll stands in an opposed contest, compared in order.
+    fn standing(&self) -> Option<(isize, isize)> {
+        match (self.system, &self.versus) {
+            _ if self.expr.is_pool() => Some((self.total, 0)),
+            (GameSystem::Gurps, Some(_)) => Some((self.margin()?, 0)),
+            (GameSystem::D100, Some((target, _))) => Some((success_level(self.total, *target), *target)),
+            (GameSystem::Gurps, None) | (GameSystem::D100, None) => None,
+            _ => Some((self.total, 0)),
+        }
+    }
+
+    /// The total and outcome of the roll, e.g. `Total:  9 vs 12: Success by 3`.
+    pub fn summary(&self) -> String {
+        let label = if self.expr.is_pool() { "Successes" } else { "Total" };
+
+        let mut summary = match &self.versus {
+            Some((target, Some(tag))) => format!("{}: {:>2} vs {} {}", label, self.total, tag, target),
+            Some((target, None)) => format!("{}: {:>2} vs {}", label, self.total, target),
+            None => format!("{}: {}", label, self.total),
+        };
+
+        if let Some(outcome) = self.outcome() {
+            summary.push_str(": ");
+            summary.push_str(&outcome);
+        }
+
+        summary
+    }
+
+    /// The rolled expression in Discord markdown, with any dropped dice struck through.
+    pub fn markdown(&self) -> String {
+        self.expr.markdown()
+    }
+
+    fn outcome(&self) -> Option<String> {
+        if self.expr.is_pool() {
+            let botch = if self.expr.is_botch() { "Botch! " } else { "" };
+
+            return match &self.versus {
+                Some((target, _)) if self.total < *target => {
+                    Some(format!("{}Failure by {}", botch, target - self.total))
+                }
+                Some((target, _)) => Some(format!("{}Success by {}", botch, self.total - target)),
+                None if self.expr.is_botch() => Some(String::from("Botch!")),
+                None => None,
+            };
+        }
+
+        match (self.system, &self.versus) {
+            (GameSystem::Pbta, _) => Some(self.system.resolve(self.total, 0, None)),
+            (GameSystem::Fate, None) => Some(ladder(self.total)),
+            (system, Some((target, _))) => Some(system.resolve(self.total, *target, self.natural())),
+            (_, None) => None,
+        }
+    }
+}
+
+impl fmt::Display for Roll {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.expr, self.summary())
+    }
+}
+
+/// A game system, deciding how a roll against a target is resolved.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameSystem {
+    Gurps,
+    D20,
+    D100,
+    Pbta,
+    Fate,
+}
+
+impl Default for GameSystem {
+    fn default() -> Self {
+        GameSystem::Gurps
+    }
+}
+
+/// A critical success or failure.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Critical {
+    Success,
+    Failure,
+}
+
+impl GameSystem {
+    /// Describe the outcome of a roll against a target number.
+    ///
+    /// `natural` is the first die kept in the roll, for systems with natural criticals.
+    pub fn resolve(self, total: isize, target: isize, natural: Option<isize>) -> String {
+        match self {
+            // GURPS 4th Edition success roll.
+            GameSystem::Gurps => {
+                let margin = target - total; // Roll under.
+
+                match self.critical(total, Some(target), None) {
+                    Some(Critical::Success) => format!("Critical Success, Margin of {}", margin),
+                    Some(Critical::Failure) => format!("Critical Failure, Margin of {}", margin.abs()),
+                    None if total > 16 => format!("Automatic Failure, Margin of {}", margin),
+                    None if margin < 0 => format!("Failure by {}", margin.abs()),
+                    None => format!("Success by {}", margin),
+                }
+            }
+
+            // d20 roll over a DC, with natural 1s and 20s.
+            GameSystem::D20 => {
+                let margin = total - target;
+
+                match self.critical(total, Some(target), natural) {
+                    Some(Critical::Success) => format!("Natural 20, Critical Success, Margin of {}", margin),
+                    Some(Critical::Failure) => format!("Natural 1, Critical Failure, Margin of {}", margin),
+                    None if margin < 0 => format!("Failure by {}", margin.abs()),
+                    None => format!("Success by {}", margin),
+                }
+            }
+
+            // Call of Cthulhu 7th Edition percentile roll under.
+            GameSystem::D100 => {
+                let critical = self.critical(total, Some(target), None);
+
+                if critical == Some(Critical::Success) {
+                    String::from("Critical Success")
+                } else if critical == Some(Critical::Failure) {
+                    String::from("Fumble")
+                } else if total <= target / 5 {
+                    String::from("Extreme Success")
+                } else if total <= target / 2 {
+                    String::from("Hard Success")
+                } else if total <= target {
+                    String::from("Regular Success")
+                } else {
+                    String::from("Failure")
+                }
+            }
+
+            // Fate Core shifts against an opposition.
+            GameSystem::Fate => {
+                let shifts = total - target;
+
+                if shifts >= 3 {
+                    format!("{}, Success with Style by {} shifts", ladder(total), shifts)
+                } else if shifts > 0 {
+                    format!("{}, Success by {} shift{}", ladder(total), shifts, if shifts == 1 { "" } else { "s" })
+                } else if shifts == 0 {
+                    format!("{}, Tie", ladder(total))
+                } else {
+                    format!("{}, Failure by {} shift{}", ladder(total), -shifts, if shifts == -1 { "" } else { "s" })
+                }
+            }
+
+            // Powered by the Apocalypse 2d6 move, without a target.
+            GameSystem::Pbta => {
+                if total >= 10 {
+                    String::from("Strong Hit (10+)")
+                } else if total >= 7 {
+                    String::from("Weak Hit (7–9)")
+                } else {
+                    String::from("Miss (6-)")
+                }
+            }
+        }
+    }
+
+    /// Whether a roll succeeds against a target, ignoring any natural criticals.
+    ///
+    /// A Fate tie is not a success, and a PbtA roll succeeds on any hit.
+    pub fn succeeds(self, total: isize, target: isize) -> bool {
+        match (self, self.critical(total, Some(target), None)) {
+            (_, Some(critical)) => critical == Critical::Success,
+            (GameSystem::Gurps, None) => total <= 16 && total <= target,
+            (GameSystem::D20, None) => total >= target,
+            (GameSystem::D100, None) => total <= target,
+            (GameSystem::Fate, None) => total > target,
+            (GameSystem::Pbta, None) => total >= 7,
+        }
+    }
+
+    /// Whether a roll is a critical success or failure, with or without a target.
+    ///
+    /// Without a target, only the criticals that don't depend on one are found.
+    pub fn critical(self, total: isize, target: Option<isize>, natural: Option<isize>) -> Option<Critical> {
+        match (self, target) {
+            (GameSystem::Gurps, Some(target)) => {
+                if total < 5 || (target > 14 && total < 6) || (target > 15 && total < 7) {
+                    Some(Critical::Success)
+                } else if (total > 16 && !(target > 15 && total == 17)) || target - total <= -10 {
+                    Some(Critical::Failure)
+                } else {
+                    None
+                }
+            }
+            (GameSystem::Gurps, None) if total < 5 => Some(Critical::Success),
+            (GameSystem::Gurps, None) if total > 17 => Some(Critical::Failure),
+
+            (GameSystem::D20, _) if natural == Some(20) => Some(Critical::Success),
+            (GameSystem::D20, _) if natural == Some(1) => Some(Critical::Failure),
+
+            (GameSystem::D100, _) if total == 1 => Some(Critical::Success),
+            (GameSystem::D100, Some(target)) if total == 100 || (target < 50 && total > 95) => Some(Critical::Failure),
+            (GameSystem::D100, None) if total == 100 => Some(Critical::Failure),
+
+            _ => None,
+        }
+    }
+}
+
+/// The winner of an opposed roll, and what they won by.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Contest {
+    First(isize),
+    Second(isize),
+    Tie,
+}
+
+/// Resolve an opposed roll between two participants, or `None` if the game
+/// system needs a target for each roll and one is missing.
+///
+/// A GURPS Quick Contest compares margins of success. A Call of Cthulhu
+/// opposed roll compares levels of success, then skill. Otherwise, the
+/// higher total (or more successes) wins.
+pub fn contest(first: &Roll, second: &Roll) -> Option<Contest> {
+    let (a, b) = (first.standing()?, second.standing()?);
+
+    Some(match a.cmp(&b) {
+        Ordering::Greater => Contest::First(a.0 - b.0),
+        Ordering::Less => Contest::Second(b.0 - a.0),
+        Ordering::Equal => Contest::Tie,
+    })
+}
+
+/// The level of success of a Call of Cthulhu roll, from a fumble at zero to a
+/// critical success at five.
+fn success_level(total: isize, target: isize) -> isize {
+    if total == 1 {
+        5
+    } else if total == 100 || (target < 50 && total > 95) {
+        0
+    } else if total <= target / 5 {
+        4
+    } else if total <= target / 2 {
+        3
+    } else if total <= target {
+        2
+    } else {
+        1
+    }
+}
+
+/// The chi-square statistic for observed counts of equally likely outcomes,
+/// and the probability of a result at least that extreme from fair dice.
+pub fn chi_square(observed: &[u64]) -> Option<(f64, f64)> {
+    let n = observed.iter().sum::<u64>() as f64;
+
+    if observed.len() < 2 || n == 0.0 {
+        return None;
+    }
+
+    let expected = n / observed.len() as f64;
+    let statistic = observed.iter().map(|&o| (o as f64 - expected).powi(2) / expected).sum::<f64>();
+    let freedom = (observed.len() - 1) as f64;
+
+    Some((statistic, gamma_q(freedom / 2.0, statistic / 2.0)))
+}
+
+/// The natural logarithm of the gamma function, by the Lanczos approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.001_208_650_973_866_179,
+        -0.000_005_395_239_384_953,
+    ];
+
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let series = COEFFICIENTS
+        .iter()
+        .enumerate()
+        .fold(1.000_000_000_190_015, |sum, (i, c)| sum + c / (x + 1.0 + i as f64));
+
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+/// The regularized upper incomplete gamma function, Q(a, x).
+fn gamma_q(a: f64, x: f64) -> f64 {
+    const ITERATIONS: usize = 200;
+    const EPSILON: f64 = 1e-14;
+    const TINY: f64 = 1e-300;
+
+    if x <= 0.0 {
+        return 1.0;
+    }
+
+    let scale = (-x + a * x.ln() - ln_gamma(a)).exp();
+
+    if x < a + 1.0 {
+        // The series for P(a, x) converges quickly here.
+        let mut term = 1.0 / a;
+        let mut sum = term;
+
+        for n in 1..ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+
+        1.0 - sum * scale
+    } else {
+        // Otherwise, Lentz's method for the continued fraction for Q(a, x).
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / TINY;
+        let mut d = 1.0 / b;
+        let mut h = d;
+
+        for n in 1..ITERATIONS {
+            let an = -(n as f64) * (n as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            d = if d.abs() < TINY { TINY } else { d };
+            c = b + an / c;
+            c = if c.abs() < TINY { TINY } else { c };
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+
+        h * scale
+    }
+}
+
+/// Describe a result on the Fate ladder, e.g. `Great (+4)`.
+pub fn ladder(total: isize) -> String {
+    let name = match total {
+        t if t >= 8 => "Legendary",
+        7 => "Epic",
+        6 => "Fantastic",
+        5 => "Superb",
+        4 => "Great",
+        3 => "Good",
+        2 => "Fair",
+        1 => "Average",
+        0 => "Mediocre",
+        -1 => "Poor",
+        _ => "Terrible",
+    };
+
+    format!("{} ({:+})", name, total)
+}
+
+impl fmt::Display for GameSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameSystem::Gurps => write!(f, "GURPS"),
+            GameSystem::D20 => write!(f, "d20"),
+            GameSystem::D100 => write!(f, "d100"),
+            GameSystem::Pbta => write!(f, "PbtA"),
+            GameSystem::Fate => write!(f, "Fate"),
+        }
+    }
+}
+
+impl FromStr for GameSystem {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "gurps" => Ok(GameSystem::Gurps),
+            "d20" | "dnd" | "5e" => Ok(GameSystem::D20),
+            "d100" | "coc" | "percentile" => Ok(GameSystem::D100),
+            "pbta" | "apocalypse" => Ok(GameSystem::Pbta),
+            "fate" | "fudge" => Ok(GameSystem::Fate),
+            other => Err(format!("unknown game system `{}`", other)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Rolls {
+    dice: Vec<Die>,
+    /// Every face that came up, in order, before any modifier and including
+    /// results discarded by rerolling.
+    samples: Vec<isize>,
+    /// The number of sides on these dice, or zero for Fate dice.
+    sides: isize,
+}
+
+impl Rolls {
+    /// Roll `count` dice, failing once the whole roll has rolled too many.
+    fn roll<R: Rng>(rng: &mut R, count: usize, low: isize, high: isize, mods: &[Modifier], limits: &Limits, rolled: &mut usize) -> Result<Self, RollError> {
+        let die = Uniform::new_inclusive(low, high);
+        let mut rolls = Vec::with_capacity(count);
+        let mut samples = Vec::with_capacity(count);
+
+        let explode = mods.iter().find_map(|m| match m {
+            Modifier::Explode(kind, on) => Some((kind, on.unwrap_or(Compare::Eq(high)))),
+            _ => None,
+        });
+
+        let reroll = mods.iter().find_map(|m| match m {
+            Modifier::Reroll(once, on) => Some((*once, *on)),
+            _ => None,
+        });
+
+        // Roll a single die, rerolling as required and recording the discarded results.
+        let mut sample = |rerolls: &mut Vec<isize>| -> Result<isize, RollError> {
+            limits.spend(rolled)?;
+            let mut value = die.sample(rng);
+            samples.push(value);
+
+            if let Some((once, on)) = reroll {
+                for _ in 0..if once { 1 } else { REROLL_LIMIT } {
+                    if !on.matches(value) {
+                        break;
+                    }
+
+                    rerolls.push(value);
+                    limits.spend(rolled)?;
+                    value = die.sample(rng);
+                    samples.push(value);
+                }
+            }
+
+            Ok(value)
+        };
+
+        for _ in 0..count {
+            let mut rerolls = Vec::new();
+            let value = sample(&mut rerolls)?;
+
+            match explode {
+                None => rolls.push(Die { rerolls, ..Die::new(value) }),
+                Some((Explosion::Compound, on)) => {
+                    let mut parts = vec![value];
+
+                    while on.matches(parts[parts.len() - 1]) && parts.len() <= EXPLODE_LIMIT {
+                        parts.push(sample(&mut rerolls)?);
+                    }
+
+                    rolls.push(Die { value: parts.iter().sum(), exploded: false, rerolls, parts });
+                }
+                Some((kind, on)) => {
+                    let mut value = value;
+                    let mut raw = value;
+
+                    for _ in 0..EXPLODE_LIMIT {
+                        if !on.matches(raw) {
+                            break;
+                        }
+
+                        rolls.push(Die { exploded: true, rerolls, ..Die::new(value) });
+
+                        rerolls = Vec::new();
+                        raw = sample(&mut rerolls)?;
+                        value = if *kind == Explosion::Penetrate { raw - 1 } else { raw };
+                    }
+
+                    rolls.push(Die { rerolls, ..Die::new(value) });
+                }
+            }
+        }
+
+        for die in &mut rolls {
+            die.value = Rolls::clamp(mods, die.value);
+        }
+
+        Ok(Rolls { dice: rolls, samples, sides: if low == 1 { high } else { 0 } })
+    }
+
+    /// The exact distribution of rolling these dice, summed or counted as successes.
+    fn odds(count: usize, low: isize, high: isize, mods: &[Modifier]) -> Result<Dist, RollError> {
+        let face = Rolls::face_odds(low, high, mods);
+        let keep = Rolls::keep(mods, count);
+
+        let explode = mods.iter().find_map(|m| match m {
+            Modifier::Explode(kind, on) => Some((*kind, on.unwrap_or(Compare::Eq(high)))),
+            _ => None,
+        });
+
+        let pool = Rolls::pool(mods);
+        let score = |v: isize| match pool {
+            Some((success, failure)) => {
+                let v = Rolls::clamp(mods, v);
+                success.matches(v) as isize - failure.map_or(false, |f| f.matches(v)) as isize
+            }
+            None => Rolls::clamp(mods, v),
+        };
+
+        let die = match explode {
+            None => face,
+            Some((Explosion::Compound, on)) => Rolls::chain(&face, on, false, &|v| v)?,
+            // Extra dice from exploding can't be told apart from the originals when keeping.
+            Some(_) if keep.is_some() => return Err(RollError::TooComplex),
+            Some((kind, on)) => {
+                return repeat(count, &Rolls::chain(&face, on, kind == Explosion::Penetrate, &score)?);
+            }
+        };
+
+        match keep {
+            None => {
+                let die = die.into_iter().fold(Dist::new(), |mut dist, (v, p)| {
+                    *dist.entry(score(v)).or_insert(0.0) += p;
+                    dist
+                });
+
+                repeat(count, &die)
+            }
+            Some(t) => Rolls::keep_odds(count, &die, t, &score),
+        }
+    }
+
+    /// The distribution of a single die, after any rerolls.
+    fn face_odds(low: isize, high: isize, mods: &[Modifier]) -> Dist {
+        let n = (high - low + 1) as f64;
+
+        let reroll = mods.iter().find_map(|m| match m {
+            Modifier::Reroll(once, on) => Some((*once, *on)),
+            _ => None,
+        });
+
+        match reroll {
+            None => (low..=high).map(|v| (v, 1.0 / n)).collect(),
+            Some((once, on)) => {
+                let tries = if once { 1 } else { REROLL_LIMIT } as i32;
+                let p = (low..=high).filter(|&v| on.matches(v)).count() as f64 / n;
+
+                // A die stops at its first result not rerolled, or at its last reroll.
+                let stops = (0..tries).map(|k| p.powi(k)).sum::<f64>();
+                let last = p.powi(tries);
+
+                (low..=high)
+                    .map(|v| (v, (if on.matches(v) { 0.0 } else { stops } + last) / n))
+                    .collect()
+            }
+        }
+    }
+
+    /// The distribution of a die's score with any dice it adds by exploding.
+    fn chain<F: Fn(isize) -> isize>(face: &Dist, on: Compare, penetrate: bool, score: &F) -> Result<Dist, RollError> {
+        let mut dist = Dist::new();
+        let mut pending: Dist = std::iter::once((0, 1.0)).collect();
+        let mut work = 0usize;
+
+        for depth in 0..=EXPLODE_LIMIT {
+            work = work.saturating_add(pending.len().saturating_mul(face.len()));
+
+            if work > ODDS_LIMIT {
+                return Err(RollError::TooComplex);
+            }
+
+            let mut next = Dist::new();
+
+            for (&sum, &p) in &pending {
+                for (&raw, &q) in face {
+                    let value = if penetrate && depth > 0 { raw - 1 } else { raw };
+                    let sum = sum + score(value);
+
+                    if depth < EXPLODE_LIMIT && on.matches(raw) {
+                        *next.entry(sum).or_insert(0.0) += p * q;
+                    } else {
+                        *dist.entry(sum).or_insert(0.0) += p * q;
+                    }
+                }
+            }
+
+            next.retain(|_, p| *p > ODDS_EPSILON);
+
+            if next.is_empty() {
+                break;
+            }
+
+            pending = next;
+        }
+
+        Ok(dist)
+    }
+
+    /// The distribution of keeping the best (positive) or worst (negative) of `count` dice.
+    ///
+    /// Works through each face in the order dice are kept, choosing how many of the
+    /// dice still unassigned show it, and tracking the score of those kept so far.
+    fn keep_odds<F: Fn(isize) -> isize>(count: usize, die: &Dist, keep: isize, score: &F) -> Result<Dist, RollError> {
+        if die.len().saturating_mul(count).saturating_mul(count) > ODDS_LIMIT {
+            return Err(RollError::TooComplex);
+        }
+
+        let mut faces = die.iter().map(|(&v, &p)| (v, p)).collect::<Vec<_>>();
+
+        if keep.is_positive() {
+            faces.reverse();
+        }
+
+        let keep = keep.abs() as usize;
+
+        // The distribution of kept scores, by the number of dice assigned a face so far.
+        let mut state = vec![Dist::new(); count + 1];
+        state[0].insert(0, 1.0);
+        let mut work = 0usize;
+
+        for (v, p) in faces {
+            work = state.iter().enumerate().fold(work, |work, (m, dist)| {
+                work.saturating_add(dist.len().saturating_mul(count - m + 1))
+            });
+
+            if work > ODDS_LIMIT {
+                return Err(RollError::TooComplex);
+            }
+
+            let mut next = vec![Dist::new(); count + 1];
+
+            for (m, dist) in state.iter().enumerate() {
+                let mut ways = 1.0;
+
+                for j in 0..=count - m {
+                    let weight = ways * p.powi(j as i32);
+                    let kept = j.min(keep.saturating_sub(m)) as isize;
+
+                    for (&sum, &q) in dist {
+                        *next[m + j].entry(sum + kept * score(v)).or_insert(0.0) += q * weight;
+                    }
+
+                    ways = ways * (count - m - j) as f64 / (j + 1) as f64;
+                }
+            }
 
-        if ! t.iter().any(Term::is_dice) {
-            t.push(Term::Dice { n: 3, s: 6, t: None });
+            state = next;
         }
 
-        if let Some(Term::Repeat(i)) = t.iter().find(|t| t.is_repeat()) {
-            repeat = *i;
+        Ok(state.pop().unwrap_or_default())
+    }
+
+    /// The values of the dice kept by any keep, drop or advantage modifiers.
+    fn kept(&self, mods: &[Modifier]) -> Vec<isize> {
+        let keep = Rolls::keep(mods, self.dice.len());
+
+        let mut rolls = self.dice.iter().map(|d| d.value).collect::<Vec<_>>();
+
+        if let Some(t) = keep {
+            rolls.sort_unstable();
+
+            if t.is_positive() {
+                rolls.into_iter().rev().take(t.abs() as usize).collect()
+            } else {
+                rolls.into_iter().take(t.abs() as usize).collect()
+            }
         } else {
-            repeat = 1;
+            rolls
         }
+    }
 
-        if let Some(Term::Versus {s, t}) = t.iter().find(|t| t.is_versus()) {
-            versus = Some((*s, t.clone()));
+    fn sum(&self, mods: &[Modifier]) -> Result<isize, RollError> {
+        let kept = self.kept(mods);
+
+        if let Some((success, failure)) = Rolls::pool(mods) {
+            let successes = kept.iter().filter(|&&v| success.matches(v)).count() as isize;
+            let failures = kept.iter().filter(|&&v| failure.map_or(false, |f| f.matches(v))).count() as isize;
+            Ok(successes - failures)
         } else {
-            versus = None;
-        }
-
-        let terms = t.into_iter().filter(|t| ! t.is_repeat() && ! t.is_versus());
-        let mut rolls = Vec::with_capacity(repeat);
-
-        for _ in 1..=repeat {
-            let terms: Vec<_> = terms.clone().map(Term::with_rolls).collect();
-            let versus = versus.clone();
-
-            let mut last = &Term::Add;
-            let mut total = 0;
-
-            for (term, rolls) in terms.iter() {
-                match term {
-                    Term::Dice { t, .. } => {
-                        let mut rolls = rolls.clone().unwrap().0;
-                        let sum = {
-                            if let Some(t) = t {
-                                rolls.sort_unstable();
-
-                                if t.is_positive() {
-                                    rolls.iter().rev().take(t.abs() as usize).sum()
-                                } else if t.is_negative() {
-                                    rolls.iter().take(t.abs() as usize).sum()
-                                } else {
-                                    0
-                                }
-                            } else {
-                                rolls.iter().sum()
-                            }
-                        };
+            kept.iter().try_fold(0isize, |sum, &v| sum.checked_add(v)).ok_or(RollError::Overflow)
+        }
+    }
 
-                        total = last.op(total, sum);
-                    }
+    /// How many dice to keep out of `count` rolled: the best (positive) or worst (negative).
+    fn keep(mods: &[Modifier], count: usize) -> Option<isize> {
+        let count = count as isize;
 
-                    Term::Num(i) => total = last.op(total, *i),
-                    _ => last = term,
+        mods.iter().find_map(|m| match m {
+            Modifier::Keep(t, _) => Some(*t),
+            Modifier::Drop(t) if t.is_positive() => Some((count - t).max(0)),
+            Modifier::Drop(t) => Some(-(count + t).max(0)),
+            Modifier::Advantage(true) => Some(count / 2),
+            Modifier::Advantage(false) => Some(-count / 2),
+            _ => None,
+        })
+    }
 
-                }
+    /// How many dice to roll for `count`, doubled by advantage or disadvantage.
+    fn count(mods: &[Modifier], count: isize) -> isize {
+        if mods.iter().any(|m| if let Modifier::Advantage(_) = m { true } else { false }) {
+            count.saturating_mul(2)
+        } else {
+            count
+        }
+    }
+
+    /// A die's value after any minimum or maximum face.
+    fn clamp(mods: &[Modifier], value: isize) -> isize {
+        mods.iter().fold(value, |value, m| match m {
+            Modifier::Min(min) => value.max(*min),
+            Modifier::Max(max) => value.min(*max),
+            _ => value,
+        })
+    }
+
+    /// The success and failure conditions, if these dice are a success-counting pool.
+    fn pool(mods: &[Modifier]) -> Option<(Compare, Option<Compare>)> {
+        let success = mods.iter().find_map(|m| match m {
+            Modifier::Success(on) => Some(*on),
+            _ => None,
+        });
+
+        let failure = mods.iter().find_map(|m| match m {
+            Modifier::Failure(on) => Some(*on),
+            _ => None,
+        });
+
+        success.map(|success| (success, failure))
+    }
+
+    /// A pool botches when it rolls no successes and at least one failure.
+    fn is_botch(&self, mods: &[Modifier]) -> bool {
+        match Rolls::pool(mods) {
+            Some((success, Some(failure))) => {
+                let kept = self.kept(mods);
+                !kept.iter().any(|&v| success.matches(v)) && kept.iter().any(|&v| failure.matches(v))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Rolls {
+    /// The dice in Discord markdown, with any dropped by keeping struck through.
+    fn markdown(&self, mods: &[Modifier]) -> String {
+        let keep = Rolls::keep(mods, self.dice.len());
+
+        let mut dropped = vec![false; self.dice.len()];
+
+        if let Some(t) = keep {
+            let mut order = (0..self.dice.len()).collect::<Vec<_>>();
+            order.sort_by_key(|&i| self.dice[i].value);
+
+            if t.is_positive() {
+                order.reverse();
             }
 
-            rolls.push(Roll { terms, total, versus });
+            for &i in order.iter().skip(t.abs() as usize) {
+                dropped[i] = true;
+            }
         }
 
-        DiceRoll { rolls }
+        let dice = self.dice
+            .iter()
+            .zip(dropped)
+            .map(|(die, dropped)| if dropped { format!("~~{}~~", die) } else { die.to_string() })
+            .collect::<Vec<_>>();
+
+        format!("[{}]", dice.join(", "))
     }
 }
 
-impl fmt::Display for DiceRoll {
+impl fmt::Display for Rolls {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut iter = self.rolls.iter();
+        let mut iter = self.dice.iter();
         let first = match iter.next() {
             Some(first) => first,
             None => return Ok(()),
         };
 
-        write!(f, "{}", first)?;
+        write!(f, "[{}", first)?;
 
         for roll in iter {
-            write!(f, "\n{}", roll)?;
+            write!(f, ", {}", roll)?;
         }
 
-        Ok(())
+        write!(f, "]")
     }
 }
 
-impl FromStr for DiceRoll {
-    type Err = ParseRollError;
+/// A single die's result, including any dice it added by exploding or compounding,
+/// and any results discarded by rerolling it.
+#[derive(Clone, Debug)]
+struct Die {
+    value: isize,
+    exploded: bool,
+    rerolls: Vec<isize>,
+    parts: Vec<isize>,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        lazy_static! {
-            static ref RE: Regex = Regex::new(r"(?xi)
-                \d+ d (?:\d+)? (?: [bw] \d+ )?                  | # Term::Dice
-                [-+×x*/\\÷%^]                                   | # Term::<Op>
-                v(?:s|ersus)? \s+ (?:\D+?.*?-?)?? \s* -? \d+    | # Term::Vs
-                r(?:e|epeat)? \s* \d+                           | # Term::Repeat
-                -? \d+                                          # Term::Num")
-            .unwrap();
-        }
+impl Die {
+    fn new(value: isize) -> Self {
+        Die { value, exploded: false, rerolls: Vec::new(), parts: Vec::new() }
+    }
+}
 
-        let terms = RE.find_iter(&s)
-            .map(|m| Term::from_str(&s[m.start()..m.end()]))
-            .collect::<Result<Vec<Term>, _>>()?;
+impl fmt::Display for Die {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for discarded in &self.rerolls {
+            write!(f, "{}→", discarded)?;
+        }
 
-        Ok(DiceRoll::new(terms))
+        if self.parts.len() > 1 {
+            write!(f, "{}", self.parts.iter().map(isize::to_string).collect::<Vec<_>>().join("+"))
+        } else if self.exploded {
+            write!(f, "{}!", self.value)
+        } else {
+            write!(f, "{}", self.value)
+        }
     }
 }
 
-#[derive(Clone, Debug)]
-struct Roll {
-    terms: Vec<(Term, Option<Rolls>)>,
-    total: isize,
-    versus: Option<(isize, Option<String>)>,
+#[derive(Clone, Debug, PartialEq)]
+enum Modifier {
+    /// Keep the best (positive) or worst (negative) dice, and whether that was
+    /// written as `kh`/`kl` rather than `b`/`w`.
+    Keep(isize, bool),
+    /// Drop the worst (positive) or best (negative) dice.
+    Drop(isize),
+    /// Roll twice as many dice, keeping the better (advantage) or worse half.
+    Advantage(bool),
+    /// Count any face below the minimum as the minimum.
+    Min(isize),
+    /// Count any face above the maximum as the maximum.
+    Max(isize),
+    /// Roll additional dice on the given faces (by default, the highest).
+    Explode(Explosion, Option<Compare>),
+    /// Reroll dice on the given faces, either once or until they stop coming up.
+    Reroll(bool, Compare),
+    /// Count dice on the given faces as successes, instead of summing them.
+    Success(Compare),
+    /// Subtract dice on the given faces from the successes.
+    Failure(Compare),
 }
 
-impl fmt::Display for Roll {
+impl fmt::Display for Modifier {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for (term, rolls) in &self.terms {
-            match (term, rolls) {
-                (Term::Dice { .. }, Some(rolls)) => write!(f, "{}{} ", term, rolls)?,
-                (Term::Repeat(_), _) | (Term::Versus{..}, _) => (),
-                (_,_) => write!(f, "{} ", term)?,
+        match self {
+            Modifier::Keep(t, false) if t.is_positive() => write!(f, "b{}", t.abs()),
+            Modifier::Keep(t, false) if t.is_negative() => write!(f, "w{}", t.abs()),
+            Modifier::Keep(_, false) => write!(f, "x0"),
+            Modifier::Keep(t, true) if t.is_negative() => write!(f, "kl{}", t.abs()),
+            Modifier::Keep(t, true) => write!(f, "kh{}", t),
+            Modifier::Drop(t) if t.is_negative() => write!(f, "dh{}", t.abs()),
+            Modifier::Drop(t) => write!(f, "dl{}", t),
+            Modifier::Advantage(true) => write!(f, "adv"),
+            Modifier::Advantage(false) => write!(f, "dis"),
+            Modifier::Min(min) => write!(f, "min{}", min),
+            Modifier::Max(max) => write!(f, "max{}", max),
+            Modifier::Explode(kind, on) => {
+                write!(f, "{}", kind)?;
+
+                if let Some(on) = on {
+                    write!(f, "{}", on)?;
+                }
+
+                Ok(())
             }
+            Modifier::Reroll(true, on) => write!(f, "ro{}", on),
+            Modifier::Reroll(false, on) => write!(f, "r{}", on),
+            Modifier::Success(Compare::Eq(i)) => write!(f, "={}", i),
+            Modifier::Success(on) => write!(f, "{}", on),
+            Modifier::Failure(on) => write!(f, "f{}", on),
         }
+    }
+}
 
-        // TODO disambiguate (on dice?) and impl other game types.
-        //      enum Game { GURPS, d20, d100, Storyteller } ?
-        if let Some((target, tag)) = &self.versus {
-            if let Some(tag) = tag {
-                write!(f, "(Total: {:>2} vs {} {}: ", self.total, tag, target)?;
-            } else {
-                write!(f, "(Total: {:>2} vs {}: ", self.total, target)?;
-            };
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Explosion {
+    /// Each die rolled on the target faces adds another die.
+    Explode,
+    /// Each additional die is added to the one that exploded.
+    Compound,
+    /// Each additional die counts one less (Hackmaster).
+    Penetrate,
+}
 
-            let target = *target;
+impl fmt::Display for Explosion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Explosion::Explode => write!(f, "!"),
+            Explosion::Compound => write!(f, "!!"),
+            Explosion::Penetrate => write!(f, "!p"),
+        }
+    }
+}
 
-            // GURPS 4th Edition success roll.
-            let margin = target - self.total; // Roll under.
+/// A comparison against a die face.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Compare {
+    Lt(isize),
+    Le(isize),
+    Eq(isize),
+    Ge(isize),
+    Gt(isize),
+}
 
-            if self.total < 5 || (target > 14 && self.total < 6) || (target > 15 && self.total < 7) {
-                write!(f, "Critical Success, Margin of {})", margin)
-            } else if self.total > 16 || margin <= -10 {
-                if target > 15 && self.total == 17 {
-                    write!(f, "Automatic Failure, Margin of {})", margin)
-                } else {
-                    write!(f, "Critical Failure, Margin of {})", margin.abs())
-                }
-            } else if margin < 0 {
-                write!(f, "Failure by {})", margin.abs())
-            } else {
-                write!(f, "Success by {})", margin)
-            }
-        } else {
-            write!(f, "(Total: {})", self.total)
+impl Compare {
+    fn matches(self, value: isize) -> bool {
+        match self {
+            Compare::Lt(i) => value < i,
+            Compare::Le(i) => value <= i,
+            Compare::Eq(i) => value == i,
+            Compare::Ge(i) => value >= i,
+            Compare::Gt(i) => value > i,
         }
     }
 }
 
-#[derive(Clone, Debug)]
-struct Rolls(Vec<isize>);
-
-impl fmt::Display for Rolls {
+impl fmt::Display for Compare {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut iter = self.0.iter();
-        let first = match iter.next() {
-            Some(first) => first,
-            None => return Ok(()),
-        };
+        match self {
+            Compare::Lt(i) => write!(f, "<{}", i),
+            Compare::Le(i) => write!(f, "<={}", i),
+            Compare::Eq(i) => write!(f, "{}", i),
+            Compare::Ge(i) => write!(f, ">={}", i),
+            Compare::Gt(i) => write!(f, ">{}", i),
+        }
+    }
+}
 
-        write!(f, "[{}", first)?;
+impl FromStr for Compare {
+    type Err = ParseRollError;
 
-        for roll in iter {
-            write!(f, ", {}", roll)?;
-        }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let i = s.trim_start_matches(&['<', '>', '='][..]);
 
-        write!(f, "]")
+        match &s[..s.len() - i.len()] {
+            "<" => Ok(Compare::Lt(i.parse()?)),
+            "<=" => Ok(Compare::Le(i.parse()?)),
+            "" | "=" => Ok(Compare::Eq(i.parse()?)),
+            ">=" => Ok(Compare::Ge(i.parse()?)),
+            ">" => Ok(Compare::Gt(i.parse()?)),
+            _ => Err(ParseRollError::new(ParseErrorKind::Unexpected, 0..s.len(), s)),
+        }
     }
 }
 
 #[derive(Clone, Debug)]
 enum Term {
     Dice {
-        n: usize,
-        s: isize,
-        t: Option<isize>,
-    },
-    Versus {
-        s: isize,
-        t: Option<String>,
+        n: Option<usize>,
+        s: Option<isize>,
+        fate: bool,
+        mods: Vec<Modifier>,
     },
+    Versus(Option<String>),
     Repeat(usize),
     Num(isize),
-    Add,
-    Sub,
-    Mul,
-    Div,
-    Rem,
-    Pow,
+    Op(Op),
+    Open,
+    Close,
 }
 
 impl Term {
@@ -225,33 +1810,57 @@ impl Term {
     }
 
     fn is_versus(&self) -> bool {
-        if let Term::Versus{..} = self {
+        if let Term::Versus(_) = self {
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_operator(&self) -> bool {
+        if let Term::Op(_) = self {
             true
         } else {
             false
         }
     }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
 
-    fn op(&self, lhs: isize, rhs: isize) -> isize {
+impl Op {
+    fn apply(self, lhs: isize, rhs: isize) -> Result<isize, RollError> {
         match self {
-            Term::Add => lhs + rhs,
-            Term::Sub => lhs - rhs,
-            Term::Mul => lhs * rhs,
-            Term::Div => lhs / rhs,
-            Term::Rem => lhs % rhs,
-            Term::Pow => lhs.pow(rhs as u32),
-            _ => panic!("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"),
+            Op::Add => lhs.checked_add(rhs).ok_or(RollError::Overflow),
+            Op::Sub => lhs.checked_sub(rhs).ok_or(RollError::Overflow),
+            Op::Mul => lhs.checked_mul(rhs).ok_or(RollError::Overflow),
+            Op::Div if rhs == 0 => Err(RollError::DivideByZero),
+            Op::Div => lhs.checked_div(rhs).ok_or(RollError::Overflow),
+            Op::Rem if rhs == 0 => Err(RollError::DivideByZero),
+            Op::Rem => lhs.checked_rem(rhs).ok_or(RollError::Overflow),
+            Op::Pow if rhs < 0 => Err(RollError::NegativePower),
+            Op::Pow => lhs.checked_pow(u32::try_from(rhs).map_err(|_| RollError::Overflow)?).ok_or(RollError::Overflow),
         }
     }
+}
 
-    fn with_rolls(self) -> (Term, Option<Rolls>) {
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Term::Dice { n, s, .. } => {
-                let die = Uniform::new_inclusive(1, s);
-                let mut rng = rand::thread_rng();
-                (self, Some(Rolls(die.sample_iter(&mut rng).take(n).collect())))
-            }
-            _ => (self, None),
+            Op::Add => write!(f, "+"),
+            Op::Sub => write!(f, "-"),
+            Op::Mul => write!(f, "×"),
+            Op::Div => write!(f, "/"),
+            Op::Rem => write!(f, "%"),
+            Op::Pow => write!(f, "^"),
         }
     }
 }
@@ -259,27 +1868,32 @@ impl Term {
 impl fmt::Display for Term {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Term::Dice { n, s, t } => {
-                if let Some(t) = t {
-                    if t.is_positive() {
-                        write!(f, "{}d{}b{}", n, s, t.abs())
-                    } else if t.is_negative() {
-                        write!(f, "{}d{}w{}", n, s, t.abs())
-                    } else {
-                        write!(f, "{}d{}x0", n, s)
-                    }
-                } else {
-                    write!(f, "{}d{}", n, s)
+            Term::Dice { n, s, fate, mods } => {
+                if let Some(n) = n {
+                    write!(f, "{}", n)?;
+                }
+
+                write!(f, "d")?;
+
+                if *fate {
+                    write!(f, "F")?;
+                } else if let Some(s) = s {
+                    write!(f, "{}", s)?;
                 }
+
+                for m in mods {
+                    write!(f, "{}", m)?;
+                }
+
+                Ok(())
             }
-            Term::Repeat(_) | Term::Versus{..} => Ok(()),
+            Term::Versus(Some(tag)) => write!(f, "vs {}", tag),
+            Term::Versus(None) => write!(f, "vs"),
+            Term::Repeat(i) => write!(f, "r{}", i),
             Term::Num(i) => write!(f, "{}", i),
-            Term::Add => write!(f, "+"),
-            Term::Sub => write!(f, "-"),
-            Term::Mul => write!(f, "×"),
-            Term::Div => write!(f, "/"),
-            Term::Rem => write!(f, "%"),
-            Term::Pow => write!(f, "^"),
+            Term::Op(op) => write!(f, "{}", op),
+            Term::Open => write!(f, "("),
+            Term::Close => write!(f, ")"),
         }
     }
 }
@@ -296,53 +1910,98 @@ impl FromStr for Term {
 
         if s.starts_with('v') {
             lazy_static! { static ref RE: Regex = Regex::new(r"(?xi)
-                v(?:s|ersus)? \s+ (?: (?P<tag>\D+?.*?) -?)?? \s* (?P<target>-?\d+)
+                v(?:s|ersus)? \s+ (?P<tag>.*)
             ").unwrap(); }
 
             if let Some(cap) = RE.captures(input) {
-                let target = cap.name("target").unwrap().as_str().parse::<isize>()?;
-                let tag = cap.name("tag").map(|m| m.as_str().to_string());
-                Ok(Term::Versus { s: target, t: tag })
+                // Trim the separator from tags like `Broadsword-14`.
+                let tag = cap.name("tag")
+                    .map(|m| m.as_str().trim_end_matches(|c: char| c == '-' || c.is_whitespace()))
+                    .filter(|tag| !tag.is_empty())
+                    .map(String::from);
+                Ok(Term::Versus(tag))
             } else {
-                Err(ParseRollError::Empty)
+                Err(ParseRollError::new(ParseErrorKind::Empty, 0..0, input))
             }
         } else if s.starts_with('r') {
             let r = s.chars().skip_while(|c| !c.is_digit(10)).collect::<String>();
             Ok(Term::Repeat(r.parse()?))
         } else if s.contains('d') {
-            let d: Vec<&str> = s.split(|c| c == 'd' || c == 'b' || c == 'w').collect();
-            if s.contains('b') && d.len() == 3 {
-                Ok(Term::Dice {
-                    n: d[0].parse()?,
-                    s: d[1].parse()?,
-                    t: Some(d[2].parse()?),
-                })
-            } else if s.contains('w') && d.len() == 3 {
-                Ok(Term::Dice {
-                    n: d[0].parse()?,
-                    s: d[1].parse()?,
-                    t: Some(-d[2].parse()?),
-                })
-            } else {
-                Ok(Term::Dice {
-                    n: d[0].parse()?,
-                    s: d[1].parse().unwrap_or(6),
-                    t: None,
-                })
+            lazy_static! {
+                static ref RE: Regex = Regex::new(r"(?x)
+                    (?P<n>\d*) d (?P<s>f|\d*) (?P<mods>.*)
+                ").unwrap();
+
+                static ref MODS: Regex = Regex::new(r"(?x)
+                    (?P<k>[bw]|k[hl]) (?P<t>\d+) |
+                    (?P<drop>d[hl]) (?P<dt>\d+) |
+                    (?P<adv>adv|dis) |
+                    (?P<clamp>min|max) (?P<ct>\d+) |
+                    (?P<x>![!p]?) (?P<on>(?:[<>]=?|=)?\d+)? |
+                    (?P<r>ro?) (?P<re>(?:[<>]=?|=)?\d+) |
+                    f (?P<fail>(?:[<>]=?|=)?\d+) |
+                    (?P<pass>(?:[<>]=?|=)\d+)
+                ").unwrap();
+            }
+
+            let cap = RE.captures(&s).ok_or_else(|| ParseRollError::new(ParseErrorKind::Empty, 0..0, input))?;
+            let n = cap.name("n").map(|m| m.as_str()).filter(|m| !m.is_empty());
+            let d = cap.name("s").map(|m| m.as_str()).filter(|m| !m.is_empty());
+            let fate = d == Some("f");
+
+            let mut mods = Vec::new();
+
+            for m in MODS.captures_iter(cap.name("mods").map_or("", |m| m.as_str())) {
+                if let (Some(k), Some(t)) = (m.name("k"), m.name("t")) {
+                    let t = t.as_str().parse::<isize>()?;
+                    let best = k.as_str() == "b" || k.as_str() == "kh";
+                    mods.push(Modifier::Keep(if best { t } else { -t }, k.as_str().len() == 2));
+                } else if let (Some(d), Some(t)) = (m.name("drop"), m.name("dt")) {
+                    let t = t.as_str().parse::<isize>()?;
+                    mods.push(Modifier::Drop(if d.as_str() == "dl" { t } else { -t }));
+                } else if let Some(adv) = m.name("adv") {
+                    mods.push(Modifier::Advantage(adv.as_str() == "adv"));
+                } else if let (Some(c), Some(t)) = (m.name("clamp"), m.name("ct")) {
+                    let t = t.as_str().parse::<isize>()?;
+                    mods.push(if c.as_str() == "min" { Modifier::Min(t) } else { Modifier::Max(t) });
+                } else if let Some(x) = m.name("x") {
+                    let kind = match x.as_str() {
+                        "!!" => Explosion::Compound,
+                        "!p" => Explosion::Penetrate,
+                        _ => Explosion::Explode,
+                    };
+                    let on = m.name("on").map(|on| on.as_str().parse()).transpose()?;
+                    mods.push(Modifier::Explode(kind, on));
+                } else if let (Some(r), Some(on)) = (m.name("r"), m.name("re")) {
+                    mods.push(Modifier::Reroll(r.as_str() == "ro", on.as_str().parse()?));
+                } else if let Some(on) = m.name("fail") {
+                    mods.push(Modifier::Failure(on.as_str().parse()?));
+                } else if let Some(on) = m.name("pass") {
+                    mods.push(Modifier::Success(on.as_str().parse()?));
+                }
             }
+
+            Ok(Term::Dice {
+                n: n.map(str::parse).transpose()?,
+                s: d.filter(|_| !fate).map(str::parse).transpose()?,
+                fate,
+                mods,
+            })
         } else {
             let mut c = s.chars();
             match c.next() {
-                Some('+') => Ok(Term::Add),
-                Some('-') => Ok(Term::Sub),
-                Some('*') => Ok(Term::Mul),
-                Some('x') => Ok(Term::Mul),
-                Some('×') => Ok(Term::Mul),
-                Some('/') => Ok(Term::Div),
-                Some('\\') => Ok(Term::Div),
-                Some('÷') => Ok(Term::Div),
-                Some('%') => Ok(Term::Rem),
-                Some('^') => Ok(Term::Pow),
+                Some('+') => Ok(Term::Op(Op::Add)),
+                Some('-') => Ok(Term::Op(Op::Sub)),
+                Some('*') => Ok(Term::Op(Op::Mul)),
+                Some('x') => Ok(Term::Op(Op::Mul)),
+                Some('×') => Ok(Term::Op(Op::Mul)),
+                Some('/') => Ok(Term::Op(Op::Div)),
+                Some('\\') => Ok(Term::Op(Op::Div)),
+                Some('÷') => Ok(Term::Op(Op::Div)),
+                Some('%') => Ok(Term::Op(Op::Rem)),
+                Some('^') => Ok(Term::Op(Op::Pow)),
+                Some('(') => Ok(Term::Open),
+                Some(')') => Ok(Term::Close),
                 Some(_) => Ok(Term::Num(s.parse()?)),
                 None => unreachable!(),
             }
@@ -357,32 +2016,435 @@ impl PartialEq for Term {
 }
 
 
+/// An error parsing a dice expression, with the span of the input where it occurred.
 #[derive(Clone, Debug)]
-pub enum ParseRollError {
+pub struct ParseRollError {
+    pub kind: ParseErrorKind,
+    pub span: Range<usize>,
+    pub token: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    /// A number that couldn't be read, usually because it's too large.
     Int(ParseIntError),
+    /// A term where the expression doesn't allow one.
+    Unexpected,
+    /// Input that isn't any kind of term.
+    Unrecognised,
+    /// A parenthesis that is never closed.
+    Unclosed,
+    /// The expression ended before it was complete.
+    Incomplete,
+    /// A term that turned out to be empty.
     Empty,
+    /// A repeat that would roll nothing, e.g. `r0`.
+    NoRepeats,
+    /// An expression nested too deeply to parse.
+    TooDeep,
+}
+
+impl ParseRollError {
+    fn new(kind: ParseErrorKind, span: Range<usize>, input: &str) -> Self {
+        let token = input.get(span.clone()).unwrap_or_default().to_string();
+        ParseRollError { kind, span, token }
+    }
+
+    /// Place an error from parsing a single term at that term's span of the input.
+    fn at(self, span: Range<usize>, input: &str) -> Self {
+        ParseRollError::new(self.kind, span, input)
+    }
+
+    /// Underline the error in the expression it came from, e.g.
+    ///
+    /// ```text
+    /// 3d6 + foo
+    ///       ^^^ unrecognised input `foo`
+    /// ```
+    pub fn snippet(&self, input: &str) -> String {
+        let start = input.get(..self.span.start).map_or(0, |s| s.chars().count());
+        let width = self.token.chars().count().max(1);
+
+        format!("{}\n{}{} {}", input, " ".repeat(start), "^".repeat(width), self)
+    }
 }
 
 impl fmt::Display for ParseRollError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
-            ParseRollError::Int(ref err) => write!(f, "{}", err),
-            ParseRollError::Empty => write!(f, "cannot parse term from empty string"),
+        match self.kind {
+            ParseErrorKind::Int(ref err) => write!(f, "invalid number `{}`: {}", self.token, err),
+            ParseErrorKind::Unexpected => write!(f, "unexpected `{}`", self.token),
+            ParseErrorKind::Unrecognised => write!(f, "unrecognised input `{}`", self.token),
+            ParseErrorKind::Unclosed => write!(f, "unclosed `{}`", self.token),
+            ParseErrorKind::Incomplete => write!(f, "expression ended unexpectedly"),
+            ParseErrorKind::Empty => write!(f, "cannot parse term from empty string"),
+            ParseErrorKind::NoRepeats => write!(f, "`{}` must repeat the roll at least once", self.token),
+            ParseErrorKind::TooDeep => write!(f, "expression is nested too deeply"),
         }
     }
 }
 
 impl Error for ParseRollError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        match self {
-            ParseRollError::Int(err) => Some(err),
-            ParseRollError::Empty => None,
+        match &self.kind {
+            ParseErrorKind::Int(err) => Some(err),
+            _ => None,
         }
     }
 }
 
 impl From<ParseIntError> for ParseRollError {
     fn from(err: ParseIntError) -> ParseRollError {
-        ParseRollError::Int(err)
+        ParseRollError { kind: ParseErrorKind::Int(err), span: 0..0, token: String::new() }
+    }
+}
+
+/// An error rolling a dice expression, or working out its odds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RollError {
+    Overflow,
+    DivideByZero,
+    NegativePower,
+    NoSides,
+    TooManyDice(usize),
+    TooManyRolled(usize),
+    TooManySides(isize),
+    TooManyRepeats(usize),
+    TooLong(usize),
+    TooComplex,
+}
+
+impl fmt::Display for RollError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RollError::Overflow => write!(f, "the result is too large"),
+            RollError::DivideByZero => write!(f, "cannot divide by zero"),
+            RollError::NegativePower => write!(f, "cannot raise to a negative power"),
+            RollError::NoSides => write!(f, "dice must have at least one side"),
+            RollError::TooManyDice(max) => write!(f, "too many dice (at most {})", max),
+            RollError::TooManyRolled(max) => write!(f, "too many dice rolled, counting explosions and rerolls (at most {})", max),
+            RollError::TooManySides(max) => write!(f, "too many sides (at most {})", max),
+            RollError::TooManyRepeats(max) => write!(f, "too many repeats (at most {})", max),
+            RollError::TooLong(max) => write!(f, "the results are too long to show (at most {} characters)", max),
+            RollError::TooComplex => write!(f, "too complex to work out exactly"),
+        }
+    }
+}
+
+impl Error for RollError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roll(s: &str) -> DiceRoll {
+        s.parse::<DiceExpr>().unwrap().roll(&mut seeded(0), &Limits::default()).unwrap()
+    }
+
+    fn total(s: &str) -> isize {
+        roll(s).rolls[0].total
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(total("1d1 + 2 * 3"), 7);
+        assert_eq!(total("(1d1 + 2) * 3"), 9);
+        assert_eq!(total("1d1 * 2 ^ 3 ^ 2"), 512);
+        assert_eq!(total("1d1 - 8 / 2 - 1"), -4);
+        assert_eq!(total("1d1 + 7 % 4"), 4);
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(total("-1d1 - -2"), 1);
+        assert_eq!(total("1d1 * -(2 + 3)"), -5);
+        assert_eq!(total("-2 ^ 2 + 1d1"), -3);
+    }
+
+    #[test]
+    fn nested_dice() {
+        assert_eq!(total("(1d1 + 2)d1"), 3);
+        assert_eq!(total("2d(1d1)"), 2);
+        assert_eq!(total("3d1d1"), 3);
+        assert_eq!(roll("(1d1)d1").to_string(), "(1d1[1])d1[1] (Total: 1)");
+    }
+
+    #[test]
+    fn default_dice() {
+        for (input, low, high) in &[("", 3, 18), ("+2", 5, 20), ("5", 8, 23), ("d20", 1, 20), ("3d", 3, 18)] {
+            let total = total(input);
+            assert!(*low <= total && total <= *high, "{} rolled {}", input, total);
+        }
+    }
+
+    #[test]
+    fn keep() {
+        let dropped = roll("4d1b3 + 2");
+        assert_eq!(dropped.rolls[0].markdown(), "4d1b3[~~1~~, 1, 1, 1] + 2");
+        assert_eq!(dropped.rolls[0].summary(), "Total: 5");
+
+        let roll = roll("4d6b3");
+        let dice = &roll.rolls[0].expr;
+
+        if let Expr::Dice { rolls: Some(rolls), .. } = dice {
+            let mut values = rolls.dice.iter().map(|d| d.value).collect::<Vec<_>>();
+            values.sort_unstable();
+            assert_eq!(roll.rolls[0].total, values[1..].iter().sum::<isize>());
+        } else {
+            panic!("not a dice expression: {:?}", dice);
+        }
+
+        assert_eq!(total("4d1w2"), 2);
+
+    }
+
+    #[test]
+    fn compatible_keep() {
+        for input in &["4d6kh3", "2d20kl1", "4d6dl1", "4d6dh1", "1d20adv", "1d20dis", "2d6min2", "2d6max5", "4d6b3"] {
+            assert_eq!(input.parse::<DiceExpr>().unwrap().to_string(), *input);
+        }
+
+        assert_eq!("1d20 adv + 5".parse::<DiceExpr>().unwrap().to_string(), "1d20adv + 5");
+
+        assert_eq!(total("4d1kh3"), 3);
+        assert_eq!(total("4d1kl1"), 1);
+        assert_eq!(total("4d1dl1"), 3);
+        assert_eq!(total("4d1dh3"), 1);
+        assert_eq!(total("2d1min3"), 6);
+        assert_eq!(total("2d6max1"), 2);
+
+        let advantage = roll("1d1adv");
+        assert_eq!(advantage.rolls[0].faces().len(), 2);
+        assert_eq!(roll("1d1min3").rolls[0].faces(), vec![Face { sides: 1, value: 1 }]);
+        assert_eq!(roll("1d1ro1").rolls[0].faces().len(), 2);
+        assert_eq!(advantage.rolls[0].total, 1);
+        assert_eq!(advantage.rolls[0].markdown(), "1d1adv[~~1~~, 1]");
+
+        let mean = |s: &str| s.parse::<DiceExpr>().unwrap().odds(GameSystem::Gurps, &Limits::default()).unwrap().mean();
+        assert!((mean("1d20adv") - 13.825).abs() < 1e-9);
+        assert!((mean("1d20dis") - 7.175).abs() < 1e-9);
+        assert!((mean("4d6dl1") - mean("4d6b3")).abs() < 1e-9);
+        assert!((mean("2d6min2") - 22.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn explode() {
+        assert_eq!(total("1d1!"), EXPLODE_LIMIT as isize + 1);
+        assert_eq!(total("1d1!!"), EXPLODE_LIMIT as isize + 1);
+        assert_eq!(total("1d1!p"), 1);
+        assert_eq!(total("2d1!>1"), 2);
+    }
+
+    #[test]
+    fn reroll() {
+        assert_eq!(total("1d1r1"), 1);
+        assert_eq!(roll("1d1ro1").to_string(), "1d1ro1[1→1] (Total: 1)");
+        assert_ne!(total("1d2r1"), 1);
+        assert_ne!(total("1d2r=1"), 1);
+    }
+
+    #[test]
+    fn pool() {
+        assert_eq!(total("8d1>=1"), 8);
+        assert_eq!(total("8d1>=2"), 0);
+        assert_eq!(total("4d1>=2f1"), -4);
+        assert!(roll("4d1>=2f1").to_string().ends_with("(Successes: -4: Botch!)"));
+    }
+
+    #[test]
+    fn fate() {
+        let total = total("4dF");
+        assert!((-4..=4).contains(&total));
+        assert_eq!(ladder(4), "Great (+4)");
+        assert_eq!(ladder(-1), "Poor (-1)");
+    }
+
+    #[test]
+    fn repeat() {
+        assert_eq!(roll("1d1 r3").rolls.len(), 3);
+        assert_eq!(roll("1d1 repeat 2").rolls.len(), 2);
+        assert_eq!(roll("3d6 r3").rolls.len(), 3);
+
+        // Straight after the dice, `r` rerolls instead of repeating.
+        let reroll = roll("4d6r1");
+        assert_eq!(reroll.rolls.len(), 1);
+        assert!(reroll.rolls[0].faces().len() >= 4);
+        assert!(reroll.to_string().starts_with("4d6r1["));
+        assert_eq!(total("4d1r1"), 4);
+    }
+
+    #[test]
+    fn versus() {
+        let broadsword = roll("3d1 vs Broadsword-14");
+        assert_eq!(broadsword.rolls[0].versus, Some((14, Some(String::from("Broadsword")))));
+
+        let fast_talk = roll("3d1 vs Fast-Talk 12-2");
+        assert_eq!(fast_talk.rolls[0].versus, Some((10, Some(String::from("Fast-Talk")))));
+        assert_eq!(fast_talk.to_string(), "3d1[1, 1, 1] (Total:  3 vs Fast-Talk 10: Critical Success, Margin of 7)");
+    }
+
+    #[test]
+    fn systems() {
+        assert_eq!(GameSystem::Gurps.resolve(17, 16, None), "Automatic Failure, Margin of -1");
+        assert_eq!(GameSystem::D20.resolve(25, 15, Some(20)), "Natural 20, Critical Success, Margin of 10");
+        assert_eq!(GameSystem::D20.resolve(14, 15, Some(9)), "Failure by 1");
+        assert_eq!(GameSystem::D100.resolve(11, 60, None), "Extreme Success");
+        assert_eq!(GameSystem::D100.resolve(97, 40, None), "Fumble");
+        assert_eq!(GameSystem::Pbta.resolve(8, 0, None), "Weak Hit (7–9)");
+        assert_eq!(GameSystem::Fate.resolve(3, 0, None), "Good (+3), Success with Style by 3 shifts");
+
+        // Only a d20 rolls naturals.
+        let d20 = |s: &str| roll(s).with_system(GameSystem::D20).rolls[0].summary();
+        assert_eq!(d20("3d1 + 20 vs 15"), "Total: 23 vs 15: Success by 8");
+        assert!(!d20("3d6 + 10 vs 15").contains("Natural"));
+        assert_eq!(d20("1d1 vs 50"), "Total:  1 vs 50: Failure by 49");
+    }
+
+    #[test]
+    fn seeded_rolls_are_reproducible() {
+        let expr = "4d6!b3 + 2d20r1 - 1d4 r5".parse::<DiceExpr>().unwrap();
+        let first = expr.roll(&mut seeded(1234), &Limits::default()).unwrap().to_string();
+        let second = expr.roll(&mut seeded(1234), &Limits::default()).unwrap().to_string();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn odds() {
+        let odds = |s: &str| s.parse::<DiceExpr>().unwrap().odds(GameSystem::Gurps, &Limits::default()).unwrap();
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-6;
+
+        assert!(close(odds("3d6 vs 14-2").success().unwrap(), 160.0 / 216.0));
+        assert!(close(odds("3d6").mean(), 10.5));
+        assert!(close(odds("4d6b3").mean(), 15869.0 / 1296.0));
+        assert!(close(odds("2d20w1").mean(), 7.175));
+        assert!(close(odds("1d6!").mean(), 4.2));
+        assert!(close(odds("1d6r1").mean(), 4.0));
+        assert!(close(odds("2d10>=6 vs 1").success().unwrap(), 0.75));
+        assert!(close(odds("4dF").stddev(), (8.0f64 / 3.0).sqrt()));
+        let complex = "4d6!b3".parse::<DiceExpr>().unwrap().odds(GameSystem::Gurps, &Limits::default());
+        assert_eq!(complex.unwrap_err(), RollError::TooComplex);
+    }
+
+    #[test]
+    fn odds_limits() {
+        // Each of these passes the limit within a step or two, so they fail quickly.
+        for s in &["1d100000!>1", "2d100000", "1000d20kh500", "200d1000kh100"] {
+            let odds = s.parse::<DiceExpr>().unwrap().odds(GameSystem::Gurps, &Limits::default());
+            assert_eq!(odds.unwrap_err(), RollError::TooComplex, "{}", s);
+        }
+    }
+
+    #[test]
+    fn succeeds_agrees_with_resolve() {
+        for &system in &[GameSystem::Gurps, GameSystem::D20, GameSystem::D100, GameSystem::Pbta] {
+            for target in 0..=100 {
+                for total in 1..=100 {
+                    let outcome = system.resolve(total, target, None);
+                    let success = outcome.contains("Success") || outcome.contains("Hit");
+                    assert_eq!(system.succeeds(total, target), success, "{} {} vs {}", system, total, target);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn critical_agrees_with_resolve() {
+        for target in 3..=20 {
+            for total in 3..=18 {
+                let outcome = GameSystem::Gurps.resolve(total, target, None);
+                let critical = GameSystem::Gurps.critical(total, Some(target), None);
+
+                assert_eq!(critical == Some(Critical::Success), outcome.contains("Critical Success"));
+                assert_eq!(critical == Some(Critical::Failure), outcome.contains("Critical Failure"));
+            }
+        }
+
+        assert_eq!(GameSystem::Gurps.critical(17, None, None), None);
+        assert_eq!(GameSystem::D20.critical(5, None, Some(20)), Some(Critical::Success));
+        assert_eq!(GameSystem::D100.critical(97, Some(40), None), Some(Critical::Failure));
+        assert_eq!(GameSystem::D100.critical(97, None, None), None);
+        assert_eq!(GameSystem::Fate.critical(4, Some(0), None), None);
+
+        // Only the system's usual dice can roll criticals.
+        assert_eq!(roll("3d1").with_system(GameSystem::Gurps).rolls[0].critical(), None);
+        assert_eq!(roll("1d1").with_system(GameSystem::D100).rolls[0].critical(), None);
+    }
+
+    #[test]
+    fn contests() {
+        let contest_in = |system, a: &str, b: &str| {
+            let (a, b) = (roll(a).with_system(system), roll(b).with_system(system));
+            contest(&a.rolls[0], &b.rolls[0])
+        };
+
+        assert_eq!(contest_in(GameSystem::Gurps, "3d1 + 5 vs 12", "3d1 + 6 vs 14"), Some(Contest::Second(1)));
+        assert_eq!(contest_in(GameSystem::Gurps, "3d1 + 9 vs 10", "3d1 + 8 vs 9"), Some(Contest::Tie));
+        assert_eq!(contest_in(GameSystem::Gurps, "3d1", "3d1 vs 10"), None);
+        assert_eq!(contest_in(GameSystem::D20, "1d1 + 5", "1d1 + 3"), Some(Contest::First(2)));
+        assert_eq!(contest_in(GameSystem::D100, "1d1 + 9 vs 50", "1d1 + 9 vs 60"), Some(Contest::Second(0)));
+        assert_eq!(contest_in(GameSystem::D100, "1d1 vs 50", "1d1 + 9 vs 50"), Some(Contest::First(1)));
+        assert_eq!(contest_in(GameSystem::Fate, "4d1>=1", "3d1>=1"), Some(Contest::First(1)));
+    }
+
+    #[test]
+    fn chi_square_p_values() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-4;
+
+        assert!(close(gamma_q(0.5, 3.8415 / 2.0), 0.05));
+        assert!(close(gamma_q(2.5, 11.0705 / 2.0), 0.05));
+        assert!(close(gamma_q(9.5, 30.1435 / 2.0), 0.05));
+
+        assert_eq!(chi_square(&[10; 6]), Some((0.0, 1.0)));
+        assert_eq!(chi_square(&[0; 6]), None);
+
+        let (statistic, p) = chi_square(&[5, 5, 5, 5, 5, 35]).unwrap();
+        assert!(close(statistic, 75.0));
+        assert!(p < 1e-10);
+    }
+
+    #[test]
+    fn limits() {
+        let roll = |s: &str| s.parse::<DiceExpr>().unwrap().roll(&mut seeded(0), &Limits::default());
+
+        assert_eq!(roll("1d6 / 0").unwrap_err(), RollError::DivideByZero);
+        assert_eq!(roll("1d6 % (1d1 - 1)").unwrap_err(), RollError::DivideByZero);
+        assert_eq!(roll("1d6 ^ -1").unwrap_err(), RollError::NegativePower);
+        assert_eq!(roll("1d6 + 9223372036854775807").unwrap_err(), RollError::Overflow);
+        assert_eq!(roll("(1d1 + 1) ^ 99").unwrap_err(), RollError::Overflow);
+        assert_eq!(roll("99999999d6").unwrap_err(), RollError::TooManyDice(1000));
+        assert_eq!(roll("(1000d1 + 1)d6").unwrap_err(), RollError::TooManyDice(1000));
+        assert_eq!(roll("1000d6!>0").unwrap_err(), RollError::TooManyRolled(10_000));
+        assert_eq!(roll("1000d6!>0r<7 r20").unwrap_err(), RollError::TooManyRolled(10_000));
+        assert!(roll("1000d6").unwrap().render(100).is_none());
+        assert_eq!(roll("3d1").unwrap().render(100).unwrap(), "3d1[1, 1, 1] (Total: 3)");
+        assert_eq!(roll("1d0").unwrap_err(), RollError::NoSides);
+        assert_eq!(roll("d(1d1 - 1)").unwrap_err(), RollError::NoSides);
+        assert_eq!(roll("1d9999999").unwrap_err(), RollError::TooManySides(1_000_000));
+        assert_eq!(roll("3d6 r9999").unwrap_err(), RollError::TooManyRepeats(20));
+    }
+
+    #[test]
+    fn errors() {
+        let err = |s: &str| s.parse::<DiceExpr>().unwrap_err();
+
+        let unclosed = err("((1d6)");
+        assert_eq!(unclosed.kind, ParseErrorKind::Unclosed);
+        assert_eq!(unclosed.span, 0..1);
+
+        let unexpected = err("1d6)");
+        assert_eq!(unexpected.kind, ParseErrorKind::Unexpected);
+        assert_eq!((unexpected.span, unexpected.token.as_str()), (3..4, ")"));
+
+        assert_eq!(err("1d6 +").kind, ParseErrorKind::Incomplete);
+        assert_eq!(err("1d6 r0").kind, ParseErrorKind::NoRepeats);
+        assert_eq!(err("1d6 repeat 0").token, "repeat 0");
+        assert_eq!(err("1d6 vs DX").kind, ParseErrorKind::Incomplete);
+        assert_eq!(err(&format!("{}1{}", "(".repeat(100), ")".repeat(100))).kind, ParseErrorKind::TooDeep);
+        assert_eq!(err(&"-".repeat(100_000)).kind, ParseErrorKind::TooDeep);
+        assert!(format!("{}1{}", "(".repeat(30), ")".repeat(30)).parse::<DiceExpr>().is_ok());
+        assert_eq!(err("99999999999999999999d6").token, "99999999999999999999d6");
+
+        let unrecognised = err("3d6 + foo");
+        assert_eq!(unrecognised.snippet("3d6 + foo"), "3d6 + foo\n      ^^^ unrecognised input `foo`");
     }
 }
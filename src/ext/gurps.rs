@@ -0,0 +1,401 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A GURPS damage type, by its usual abbreviation (Basic Set, p. 379).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DamageType {
+    Burning,
+    Corrosion,
+    Crushing,
+    Cutting,
+    Fatigue,
+    Impaling,
+    SmallPiercing,
+    Piercing,
+    LargePiercing,
+    HugePiercing,
+    Toxic,
+}
+
+impl DamageType {
+    /// The wounding modifier against the torso.
+    pub fn multiplier(self) -> f64 {
+        match self {
+            DamageType::SmallPiercing => 0.5,
+            DamageType::Cutting | DamageType::LargePiercing => 1.5,
+            DamageType::Impaling | DamageType::HugePiercing => 2.0,
+            _ => 1.0,
+        }
+    }
+
+    fn is_piercing(self) -> bool {
+        match self {
+            DamageType::SmallPiercing | DamageType::Piercing => true,
+            DamageType::LargePiercing | DamageType::HugePiercing => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for DamageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DamageType::Burning => "burn",
+            DamageType::Corrosion => "cor",
+            DamageType::Crushing => "cr",
+            DamageType::Cutting => "cut",
+            DamageType::Fatigue => "fat",
+            DamageType::Impaling => "imp",
+            DamageType::SmallPiercing => "pi-",
+            DamageType::Piercing => "pi",
+            DamageType::LargePiercing => "pi+",
+            DamageType::HugePiercing => "pi++",
+            DamageType::Toxic => "tox",
+        })
+    }
+}
+
+impl FromStr for DamageType {
+    type Err = UnknownError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "burn" | "burning" => Ok(DamageType::Burning),
+            "cor" | "corr" | "corrosion" => Ok(DamageType::Corrosion),
+            "cr" | "crush" | "crushing" => Ok(DamageType::Crushing),
+            "cut" | "cutting" => Ok(DamageType::Cutting),
+            "fat" | "fatigue" => Ok(DamageType::Fatigue),
+            "imp" | "impaling" => Ok(DamageType::Impaling),
+            "pi-" => Ok(DamageType::SmallPiercing),
+            "pi" | "piercing" => Ok(DamageType::Piercing),
+            "pi+" => Ok(DamageType::LargePiercing),
+            "pi++" => Ok(DamageType::HugePiercing),
+            "tox" | "toxic" => Ok(DamageType::Toxic),
+            _ => Err(UnknownError(s.to_string())),
+        }
+    }
+}
+
+/// Where an attack landed (Basic Set, pp. 398-400).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Location {
+    Torso,
+    Vitals,
+    Skull,
+    Eye,
+    Face,
+    Neck,
+    Groin,
+    Arm,
+    Leg,
+    Hand,
+    Foot,
+}
+
+impl Location {
+    /// The penalty to hit the location deliberately.
+    pub fn penalty(self) -> isize {
+        match self {
+            Location::Torso => 0,
+            Location::Arm | Location::Leg => -2,
+            Location::Vitals | Location::Groin => -3,
+            Location::Hand | Location::Foot => -4,
+            Location::Face | Location::Neck => -5,
+            Location::Skull => -7,
+            Location::Eye => -9,
+        }
+    }
+
+    /// Natural DR at the location, on top of any armour.
+    pub fn dr(self) -> isize {
+        match self {
+            Location::Skull => 2,
+            _ => 0,
+        }
+    }
+
+    /// The wounding modifier for a damage type striking here.
+    pub fn multiplier(self, kind: DamageType) -> f64 {
+        use DamageType::*;
+
+        match (self, kind) {
+            (Location::Skull, Toxic) | (Location::Eye, Toxic) => 1.0,
+            (Location::Skull, _) | (Location::Eye, _) => 4.0,
+            (Location::Vitals, Impaling) => 3.0,
+            (Location::Vitals, k) if k.is_piercing() => 3.0,
+            (Location::Face, Corrosion) | (Location::Neck, Corrosion) | (Location::Neck, Crushing) => 1.5,
+            (Location::Neck, Cutting) => 2.0,
+            (l, Impaling) | (l, LargePiercing) | (l, HugePiercing) if l.is_extremity() => 1.0,
+            (_, kind) => kind.multiplier(),
+        }
+    }
+
+    fn is_extremity(self) -> bool {
+        match self {
+            Location::Arm | Location::Leg | Location::Hand | Location::Foot => true,
+            _ => false,
+        }
+    }
+
+    /// The least injury that cripples the location for a character with the
+    /// given HP. Any injury beyond it is lost.
+    pub fn crippling(self, hp: isize) -> Option<isize> {
+        match self {
+            Location::Arm | Location::Leg => Some(hp / 2 + 1),
+            Location::Hand | Location::Foot => Some(hp / 3 + 1),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Location::Torso => "torso",
+            Location::Vitals => "vitals",
+            Location::Skull => "skull",
+            Location::Eye => "eye",
+            Location::Face => "face",
+            Location::Neck => "neck",
+            Location::Groin => "groin",
+            Location::Arm => "arm",
+            Location::Leg => "leg",
+            Location::Hand => "hand",
+            Location::Foot => "foot",
+        })
+    }
+}
+
+impl FromStr for Location {
+    type Err = UnknownError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "torso" | "body" | "chest" => Ok(Location::Torso),
+            "vitals" | "vital" | "heart" => Ok(Location::Vitals),
+            "skull" | "head" | "brain" => Ok(Location::Skull),
+            "eye" | "eyes" => Ok(Location::Eye),
+            "face" | "jaw" => Ok(Location::Face),
+            "neck" | "throat" => Ok(Location::Neck),
+            "groin" => Ok(Location::Groin),
+            "arm" | "arms" => Ok(Location::Arm),
+            "leg" | "legs" => Ok(Location::Leg),
+            "hand" | "hands" => Ok(Location::Hand),
+            "foot" | "feet" => Ok(Location::Foot),
+            _ => Err(UnknownError(s.to_string())),
+        }
+    }
+}
+
+/// An attack's damage type, armour divisor and where it struck.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attack {
+    pub kind: DamageType,
+    pub divisor: f64,
+    pub location: Location,
+}
+
+impl Default for Attack {
+    fn default() -> Self {
+        Attack { kind: DamageType::Crushing, divisor: 1.0, location: Location::Torso }
+    }
+}
+
+/// The result of an attack's damage against a target.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Injury {
+    /// The basic damage rolled, at least 1 (or 0 for crushing).
+    pub basic: isize,
+    /// The DR left after the armour divisor, including any natural DR.
+    pub dr: isize,
+    /// The damage that got through DR.
+    pub penetrating: isize,
+    pub multiplier: f64,
+    pub injury: isize,
+    /// Whether the injury cripples a limb or extremity, capping it.
+    pub crippled: bool,
+}
+
+impl Attack {
+    /// Work out the injury from a damage roll against the target's DR, and
+    /// its HP if known, to cap injury to limbs (Basic Set, pp. 378-379).
+    pub fn wound(&self, rolled: isize, dr: isize, hp: Option<isize>) -> Injury {
+        let basic = match self.kind {
+            DamageType::Crushing => rolled.max(0),
+            _ => rolled.max(1),
+        };
+
+        let dr = dr + self.location.dr();
+        let dr = if self.divisor < 1.0 && dr == 0 { 1 } else { dr };
+        let dr = (dr as f64 / self.divisor).floor() as isize;
+
+        let penetrating = (basic - dr).max(0);
+        let multiplier = self.location.multiplier(self.kind);
+
+        let mut injury = match (penetrating as f64 * multiplier).floor() as isize {
+            0 if penetrating > 0 => 1,
+            injury => injury,
+        };
+
+        let mut crippled = false;
+
+        if let Some(cap) = hp.and_then(|hp| self.location.crippling(hp)) {
+            if injury >= cap {
+                injury = cap;
+                crippled = true;
+            }
+        }
+
+        Injury { basic, dr, penetrating, multiplier, injury, crippled }
+    }
+}
+
+/// Basic Lift in pounds for a given ST, using KYOS.
+pub fn basic_lift(st: f64) -> f64 {
+    let lift = 10f64.powf(st / 10.0) * 2.0;
+    let ord  = 10f64.powf((lift.log10() - 1.0).floor());
+    (lift / ord).round() * ord
+}
+
+/// How much a character is carrying, relative to Basic Lift (Basic Set, p. 17).
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Encumbrance {
+    None,
+    Light,
+    Medium,
+    Heavy,
+    ExtraHeavy,
+}
+
+impl Encumbrance {
+    /// The encumbrance for a load, or `None` if it's too heavy to move at all.
+    pub fn for_load(basic_lift: f64, load: f64) -> Option<Self> {
+        match load / basic_lift {
+            x if x <= 1.0 => Some(Encumbrance::None),
+            x if x <= 2.0 => Some(Encumbrance::Light),
+            x if x <= 3.0 => Some(Encumbrance::Medium),
+            x if x <= 6.0 => Some(Encumbrance::Heavy),
+            x if x <= 10.0 => Some(Encumbrance::ExtraHeavy),
+            _ => None,
+        }
+    }
+
+    /// The encumbrance level, which is also the penalty to Dodge.
+    pub fn level(self) -> isize {
+        self as isize
+    }
+
+    /// Basic Move reduced for encumbrance, rounding down but never below 1.
+    pub fn reduce_move(self, basic_move: isize) -> isize {
+        let multiplier = match self {
+            Encumbrance::None => 1.0,
+            Encumbrance::Light => 0.8,
+            Encumbrance::Medium => 0.6,
+            Encumbrance::Heavy => 0.4,
+            Encumbrance::ExtraHeavy => 0.2,
+        };
+
+        ((basic_move as f64 * multiplier).floor() as isize).max(basic_move.min(1))
+    }
+}
+
+impl fmt::Display for Encumbrance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Encumbrance::None => "None",
+            Encumbrance::Light => "Light",
+            Encumbrance::Medium => "Medium",
+            Encumbrance::Heavy => "Heavy",
+            Encumbrance::ExtraHeavy => "Extra-Heavy",
+        })
+    }
+}
+
+/// What a character can lift and move as multiples of Basic Lift (Basic Set, p. 353).
+pub const LIFTING: &[(&str, f64)] = &[
+    ("One-handed lift", 2.0),
+    ("Two-handed lift", 8.0),
+    ("Shove and knock over", 12.0),
+    ("Running shove", 24.0),
+    ("Carry on back", 15.0),
+    ("Shift slightly", 50.0),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownError(String);
+
+impl fmt::Display for UnknownError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "I don't know `{}`", self.0)
+    }
+}
+
+impl Error for UnknownError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attack(kind: &str, divisor: f64, location: &str) -> Attack {
+        Attack { kind: kind.parse().unwrap(), divisor, location: location.parse().unwrap() }
+    }
+
+    #[test]
+    fn wounding() {
+        let torso = |kind, rolled, dr| attack(kind, 1.0, "torso").wound(rolled, dr, None).injury;
+
+        assert_eq!(torso("cut", 9, 3), 9);
+        assert_eq!(torso("imp", 9, 3), 12);
+        assert_eq!(torso("pi-", 5, 0), 2);
+        assert_eq!(torso("pi-", 1, 0), 1);
+        assert_eq!(torso("cr", 3, 5), 0);
+        assert_eq!(torso("cr", -1, 0), 0);
+        assert_eq!(torso("cut", -1, 0), 1);
+
+        assert_eq!(attack("cr", 1.0, "skull").wound(10, 0, None).injury, 32);
+        assert_eq!(attack("tox", 1.0, "skull").wound(10, 0, None).injury, 8);
+        assert_eq!(attack("pi", 1.0, "vitals").wound(4, 0, None).injury, 12);
+        assert_eq!(attack("cr", 1.0, "vitals").wound(4, 0, None).injury, 4);
+        assert_eq!(attack("cut", 1.0, "neck").wound(5, 0, None).injury, 10);
+        assert_eq!(attack("cr", 1.0, "neck").wound(5, 0, None).injury, 7);
+        assert_eq!(attack("imp", 1.0, "arm").wound(5, 0, None).injury, 5);
+    }
+
+    #[test]
+    fn armour_divisors() {
+        assert_eq!(attack("pi+", 2.0, "torso").wound(10, 7, None).dr, 3);
+        assert_eq!(attack("pi+", 2.0, "torso").wound(10, 7, None).injury, 10);
+        assert_eq!(attack("cr", 0.5, "torso").wound(10, 3, None).dr, 6);
+        assert_eq!(attack("cr", 0.5, "torso").wound(10, 0, None).dr, 2);
+        assert_eq!(attack("cr", 2.0, "skull").wound(10, 4, None).dr, 3);
+    }
+
+    #[test]
+    fn crippling() {
+        let arm = attack("cut", 1.0, "arm").wound(10, 0, Some(10));
+        assert_eq!((arm.injury, arm.crippled), (6, true));
+
+        let hand = attack("cr", 1.0, "hand").wound(3, 0, Some(10));
+        assert_eq!((hand.injury, hand.crippled), (3, false));
+
+        let torso = attack("cut", 1.0, "torso").wound(10, 0, Some(10));
+        assert_eq!((torso.injury, torso.crippled), (15, false));
+    }
+
+    #[test]
+    fn encumbrance() {
+        assert_eq!(basic_lift(10.0), 20.0);
+        assert_eq!(basic_lift(12.0), 32.0);
+
+        assert_eq!(Encumbrance::for_load(20.0, 20.0), Some(Encumbrance::None));
+        assert_eq!(Encumbrance::for_load(20.0, 45.0), Some(Encumbrance::Medium));
+        assert_eq!(Encumbrance::for_load(20.0, 200.0), Some(Encumbrance::ExtraHeavy));
+        assert_eq!(Encumbrance::for_load(20.0, 201.0), None);
+
+        assert_eq!(Encumbrance::Medium.level(), 2);
+        assert_eq!(Encumbrance::Light.reduce_move(6), 4);
+        assert_eq!(Encumbrance::ExtraHeavy.reduce_move(4), 1);
+        assert_eq!(Encumbrance::ExtraHeavy.reduce_move(0), 0);
+    }
+}
@@ -1,4 +1,7 @@
 pub mod dice;
+pub mod gurps;
+pub mod tables;
+pub mod units;
 pub static EMOJI: phf::Map<&'_ str, &'_ str> = ::phf::Map {
     key: 3_213_172_566_270_843_353,
     disps: ::phf::Slice::Static(&[
@@ -0,0 +1,205 @@
+/// A table to roll on, with a row for each band of results.
+#[derive(Clone, Copy, Debug)]
+pub struct Table {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub title: &'static str,
+    /// The dice rolled, before any modifier.
+    pub dice: &'static str,
+    /// Each row, by the highest result it covers. The first row also covers
+    /// everything below it, and the last everything above it.
+    pub rows: &'static [(isize, &'static str)],
+}
+
+impl Table {
+    /// The row for a result, labelled with the range of results it covers.
+    pub fn lookup(&self, total: isize) -> (String, &'static str) {
+        let index = self.rows.iter().position(|&(high, _)| total <= high).unwrap_or(self.rows.len() - 1);
+        let (high, text) = self.rows[index];
+        let last = index == self.rows.len() - 1;
+
+        let label = match index.checked_sub(1).map(|i| self.rows[i].0 + 1) {
+            None if self.rows.len() > 1 => format!("{} or less", high),
+            Some(low) if last => format!("{} or more", low),
+            Some(low) if low < high => format!("{}–{}", low, high),
+            _ => high.to_string(),
+        };
+
+        (label, text)
+    }
+}
+
+/// Find a table by its name or any alias.
+pub fn table(name: &str) -> Option<&'static Table> {
+    let name = name.to_lowercase();
+    TABLES.iter().find(|t| t.name == name || t.aliases.contains(&name.as_str()))
+}
+
+pub const TABLES: &[Table] = &[
+    Table {
+        name: "reaction",
+        aliases: &["react", "reactions"],
+        title: "Reaction Table (B560)",
+        dice: "3d6",
+        rows: &[
+            (0, "**Disastrous.** Hates you, and will act against you however they can."),
+            (3, "**Very Bad.** Dislikes you intensely, and will work against you."),
+            (6, "**Bad.** Doesn't care for you. Won't help without a very good reason or payment."),
+            (9, "**Poor.** Unimpressed and unhelpful, though may go along for a good bribe."),
+            (12, "**Neutral.** Ignores you where possible, and deals fairly if not."),
+            (15, "**Good.** Likes you, and will help within reason."),
+            (18, "**Very Good.** Thinks highly of you, and will go out of their way to help."),
+            (19, "**Excellent.** Extremely impressed, and will do almost anything reasonable for you."),
+        ],
+    },
+    Table {
+        name: "fright",
+        aliases: &["fear", "frightcheck"],
+        title: "Fright Check Table (B360)",
+        dice: "3d6",
+        rows: &[
+            (5, "Stunned for one second, then recover automatically."),
+            (7, "Stunned for one second. Each second after, roll unmodified Will to snap out of it."),
+            (9, "Stunned for one second. Each second after, roll Will, with the original check's modifiers, to snap out of it."),
+            (10, "Stunned for 1d seconds. Each second after, roll modified Will to snap out of it."),
+            (11, "Stunned for 2d seconds. Each second after, roll modified Will to snap out of it."),
+            (12, "Lose your lunch: retch for (25 − HT) seconds, then roll modified Will each second to recover."),
+            (13, "Acquire a new mental quirk."),
+            (14, "Lose 1d FP, and stunned for 1d seconds as for 10."),
+            (15, "Lose 1d FP, and stunned for 2d seconds as for 11."),
+            (16, "Stunned for 1d seconds as for 10, and acquire a new mental quirk."),
+            (17, "Faint for 1d minutes. Each minute after, roll HT to recover."),
+            (18, "Faint for 1d minutes as for 17, and roll HT immediately; on a failure, take 1 HP of injury as you collapse."),
+            (19, "Severe faint, lasting 2d minutes. Each minute after, roll HT to recover. Take 2 HP of injury."),
+            (20, "Faint bordering on shock, lasting 4d minutes. Also lose 1d FP."),
+            (21, "Panic. Run, scream or cower for 1d minutes, then roll modified Will each minute to recover."),
+            (22, "Acquire a new −10-point Delusion."),
+            (23, "Acquire a new −10-point Phobia or other −10-point mental disadvantage."),
+            (24, "Major physical effect, set by the GM: hair turns white, age five years overnight, and so on. Acquire −15 points of physical disadvantages."),
+            (25, "Acquire a new −15-point mental disadvantage."),
+            (26, "Faint for 1d minutes as for 17, and acquire a new −10-point Delusion."),
+            (27, "Light coma. Unconscious for 30 minutes, then roll HT every 30 minutes to recover. All skills at −2 for 6 hours after."),
+            (28, "Coma. Unconscious for 1d hours, then roll HT each hour to recover. All skills at −2 for 6 hours after."),
+            (29, "Catatonia. Stare into space for 1d days, then roll HT each day to recover."),
+            (30, "Seizure. Lose control of your body for 1d minutes, and take 1d FP."),
+            (31, "Stricken by a mild heart attack or stroke. Take 2d injury."),
+            (32, "Total panic, as for 21 but lasting 1d hours."),
+            (33, "Acquire a new −15-point Delusion."),
+            (34, "Acquire a new −15-point Phobia or other −15-point mental disadvantage."),
+            (35, "Severe physical effect, as for 24 but acquiring −20 points of physical disadvantages."),
+            (36, "Severe physical effect as for 35, and a new −15-point mental disadvantage."),
+            (37, "As for 36, and lose 1 point of IQ permanently."),
+        ],
+    },
+    Table {
+        name: "crithit",
+        aliases: &["critical", "criticalhit", "crit"],
+        title: "Critical Hit Table (B556)",
+        dice: "3d6",
+        rows: &[
+            (3, "The blow does triple damage."),
+            (4, "The target's DR protects at half value, after any armour divisor."),
+            (5, "The blow does double damage."),
+            (6, "The blow does maximum normal damage."),
+            (7, "If any damage penetrates DR, treat it as a major wound."),
+            (8, "If any damage penetrates DR, it causes double shock (to −8). A limb or extremity is crippled for 2d seconds."),
+            (11, "Normal damage only."),
+            (12, "Normal damage, and the victim drops anything they're holding."),
+            (13, "If any damage penetrates DR, treat it as a major wound."),
+            (14, "If any damage penetrates DR, it causes double shock (to −8). A limb or extremity is crippled for 2d seconds."),
+            (15, "The blow does maximum normal damage."),
+            (16, "The blow does double damage."),
+            (17, "The target's DR protects at half value, after any armour divisor."),
+            (18, "The blow does triple damage."),
+        ],
+    },
+    Table {
+        name: "critmiss",
+        aliases: &["miss", "criticalmiss", "fumble"],
+        title: "Critical Miss Table (B556)",
+        dice: "3d6",
+        rows: &[
+            (4, "Your weapon breaks and is useless."),
+            (5, "You hit yourself in the arm or leg (even chance of each) for normal damage."),
+            (6, "You hit yourself in the arm or leg (even chance of each) for half damage."),
+            (7, "You lose your balance. You can do nothing else until your next turn, and all active defences are at −2."),
+            (8, "Your weapon turns in your hand. It takes an extra Ready manoeuvre before you can use it again."),
+            (11, "You drop your weapon."),
+            (12, "Your weapon turns in your hand. It takes an extra Ready manoeuvre before you can use it again."),
+            (13, "You lose your balance. You can do nothing else until your next turn, and all active defences are at −2."),
+            (14, "A swung weapon flies 1d yards from your hand, forwards or backwards. Otherwise, you drop it."),
+            (15, "You strain your shoulder! Your weapon arm is out of action for 30 minutes."),
+            (16, "You fall down!"),
+            (18, "Your weapon breaks and is useless."),
+        ],
+    },
+    Table {
+        name: "headblow",
+        aliases: &["head", "criticalheadblow"],
+        title: "Critical Head Blow Table (B556)",
+        dice: "3d6",
+        rows: &[
+            (3, "Normal damage, and the victim is knocked unconscious. Roll HT every 30 minutes to recover."),
+            (5, "Normal damage. A crushing blow also knocks the victim off balance: Do Nothing next turn."),
+            (7, "Normal damage, and the victim is knocked off balance: Do Nothing next turn, though they may defend."),
+            (8, "Normal damage. A crushing blow also deafens the victim in one ear until healed."),
+            (11, "Normal damage only."),
+            (12, "Normal damage. A crushing blow also deafens the victim until healed."),
+            (13, "Normal damage. If any damage penetrates DR, the victim is blinded in one eye until healed."),
+            (14, "Normal damage, and the victim drops anything they're holding."),
+            (15, "The blow does maximum normal damage."),
+            (16, "Normal damage, and the victim is stunned. Roll HT each turn to recover."),
+            (17, "Normal damage, with double shock. A crushing blow also stuns the victim."),
+            (18, "Normal damage, and the victim is knocked unconscious. Roll HT every 30 minutes to recover."),
+        ],
+    },
+    Table {
+        name: "hitloc",
+        aliases: &["location", "hitlocation", "loc"],
+        title: "Hit Location Table (B552)",
+        dice: "3d6",
+        rows: &[
+            (4, "**Skull** (−7)"),
+            (5, "**Face** (−5)"),
+            (7, "**Right leg** (−2)"),
+            (8, "**Right arm** (−2)"),
+            (10, "**Torso** (0)"),
+            (11, "**Groin** (−3)"),
+            (12, "**Left arm** (−2)"),
+            (14, "**Left leg** (−2)"),
+            (15, "**Hand** (−4)"),
+            (16, "**Foot** (−4)"),
+            (18, "**Neck** (−5)"),
+        ],
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookups() {
+        let reaction = table("React").unwrap();
+        assert_eq!(reaction.lookup(-4).0, "0 or less");
+        assert_eq!(reaction.lookup(2).0, "1–3");
+        assert_eq!(reaction.lookup(12).0, "10–12");
+        assert_eq!(reaction.lookup(25).0, "19 or more");
+
+        let location = table("hitloc").unwrap();
+        assert_eq!(location.lookup(9), ("9–10".to_string(), "**Torso** (0)"));
+        assert_eq!(location.lookup(11), ("11".to_string(), "**Groin** (−3)"));
+
+        assert_eq!(table("fright").unwrap().lookup(40).0, "37 or more");
+        assert!(table("nonsense").is_none());
+    }
+
+    #[test]
+    fn tables_cover_their_dice() {
+        for t in TABLES {
+            assert!(!t.rows.is_empty(), "{} has no rows", t.name);
+            assert!(t.rows.windows(2).all(|w| w[0].0 < w[1].0), "{} is out of order", t.name);
+            assert!(t.rows.last().unwrap().0 >= 18, "{} ends early", t.name);
+        }
+    }
+}
@@ -0,0 +1,679 @@
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// The powers of each SI base unit in a quantity: metre, kilogram, second,
+/// ampere, kelvin, mole and candela.
+type Dims = [i8; 7];
+
+const NONE: Dims = [0, 0, 0, 0, 0, 0, 0];
+const LENGTH: Dims = [1, 0, 0, 0, 0, 0, 0];
+const AREA: Dims = [2, 0, 0, 0, 0, 0, 0];
+const VOLUME: Dims = [3, 0, 0, 0, 0, 0, 0];
+const MASS: Dims = [0, 1, 0, 0, 0, 0, 0];
+const TIME: Dims = [0, 0, 1, 0, 0, 0, 0];
+const FREQUENCY: Dims = [0, 0, -1, 0, 0, 0, 0];
+const SPEED: Dims = [1, 0, -1, 0, 0, 0, 0];
+const ACCELERATION: Dims = [1, 0, -2, 0, 0, 0, 0];
+const FORCE: Dims = [1, 1, -2, 0, 0, 0, 0];
+const PRESSURE: Dims = [-1, 1, -2, 0, 0, 0, 0];
+const ENERGY: Dims = [2, 1, -2, 0, 0, 0, 0];
+const POWER: Dims = [2, 1, -3, 0, 0, 0, 0];
+const CURRENT: Dims = [0, 0, 0, 1, 0, 0, 0];
+const CHARGE: Dims = [0, 0, 1, 1, 0, 0, 0];
+const VOLTAGE: Dims = [2, 1, -3, -1, 0, 0, 0];
+const RESISTANCE: Dims = [2, 1, -3, -2, 0, 0, 0];
+const TEMPERATURE: Dims = [0, 0, 0, 0, 1, 0, 0];
+const AMOUNT: Dims = [0, 0, 0, 0, 0, 1, 0];
+const LUMINOSITY: Dims = [0, 0, 0, 0, 0, 0, 1];
+
+/// The symbols of the base units, in the order they're printed.
+const BASE: [(usize, &str); 7] = [(3, "A"), (6, "cd"), (4, "K"), (1, "kg"), (0, "m"), (5, "mol"), (2, "s")];
+
+const INCH: f64 = 0.0254;
+const FOOT: f64 = 12.0 * INCH;
+const YARD: f64 = 3.0 * FOOT;
+const MILE: f64 = 5280.0 * FOOT;
+const NAUTICAL_MILE: f64 = 1852.0;
+const POUND: f64 = 0.453_592_37;
+const GALLON: f64 = 231.0 * INCH * INCH * INCH;
+const IMPERIAL_GALLON: f64 = 4.546_09e-3;
+const HOUR: f64 = 3600.0;
+const DAY: f64 = 24.0 * HOUR;
+const YEAR: f64 = 365.242_198_781 * DAY;
+const GRAVITY: f64 = 9.806_65;
+const CALORIE: f64 = 4.184;
+
+/// Every unit known, by each of its names, with its size in SI base units.
+/// US customary units are used where they differ from imperial ones, which
+/// are named with an `imperial` or `uk` prefix.
+const UNITS: &[(&[&str], f64, Dims)] = &[
+    // Length
+    (&["m", "meter", "metre"], 1.0, LENGTH),
+    (&["in", "inch"], INCH, LENGTH),
+    (&["ft", "foot", "feet"], FOOT, LENGTH),
+    (&["yd", "yard"], YARD, LENGTH),
+    (&["mi", "mile"], MILE, LENGTH),
+    (&["nmi", "nauticalmile"], NAUTICAL_MILE, LENGTH),
+    (&["mil", "thou"], INCH / 1000.0, LENGTH),
+    (&["hand"], 4.0 * INCH, LENGTH),
+    (&["fathom"], 6.0 * FOOT, LENGTH),
+    (&["rod", "pole", "perch"], 16.5 * FOOT, LENGTH),
+    (&["chain"], 66.0 * FOOT, LENGTH),
+    (&["furlong"], 660.0 * FOOT, LENGTH),
+    (&["league"], 3.0 * MILE, LENGTH),
+    (&["micron"], 1e-6, LENGTH),
+    (&["angstrom"], 1e-10, LENGTH),
+    (&["au", "astronomicalunit"], 149_597_870_700.0, LENGTH),
+    (&["ly", "lightyear"], 9_460_730_472_580_800.0, LENGTH),
+    (&["pc", "parsec"], 3.085_677_581_491_367e16, LENGTH),
+    // Area
+    (&["acre"], 43_560.0 * FOOT * FOOT, AREA),
+    (&["ha", "hectare"], 1e4, AREA),
+    (&["are"], 100.0, AREA),
+    // Volume
+    (&["l", "L", "liter", "litre"], 1e-3, VOLUME),
+    (&["cc"], 1e-6, VOLUME),
+    (&["gal", "gallon"], GALLON, VOLUME),
+    (&["qt", "quart"], GALLON / 4.0, VOLUME),
+    (&["pt", "pint"], GALLON / 8.0, VOLUME),
+    (&["cup"], GALLON / 16.0, VOLUME),
+    (&["floz", "fluidounce"], GALLON / 128.0, VOLUME),
+    (&["tbsp", "tablespoon"], GALLON / 256.0, VOLUME),
+    (&["tsp", "teaspoon"], GALLON / 768.0, VOLUME),
+    (&["bbl", "barrel"], 42.0 * GALLON, VOLUME),
+    (&["impgal", "imperialgallon", "ukgallon"], IMPERIAL_GALLON, VOLUME),
+    (&["impqt", "imperialquart", "ukquart"], IMPERIAL_GALLON / 4.0, VOLUME),
+    (&["imppt", "imperialpint", "ukpint"], IMPERIAL_GALLON / 8.0, VOLUME),
+    (&["imperialgill", "ukgill"], IMPERIAL_GALLON / 32.0, VOLUME),
+    (&["impfloz", "imperialfluidounce", "ukfluidounce"], IMPERIAL_GALLON / 160.0, VOLUME),
+    // Mass
+    (&["g", "gram", "gramme"], 1e-3, MASS),
+    (&["t", "tonne", "metricton"], 1000.0, MASS),
+    (&["lb", "lbm", "pound"], POUND, MASS),
+    (&["oz", "ounce"], POUND / 16.0, MASS),
+    (&["gr", "grain"], POUND / 7000.0, MASS),
+    (&["stone"], 14.0 * POUND, MASS),
+    (&["ton", "shortton"], 2000.0 * POUND, MASS),
+    (&["longton"], 2240.0 * POUND, MASS),
+    (&["slug"], POUND * GRAVITY / FOOT, MASS),
+    (&["ct", "carat"], 2e-4, MASS),
+    // Time
+    (&["s", "sec", "second"], 1.0, TIME),
+    (&["min", "minute"], 60.0, TIME),
+    (&["h", "hr", "hour"], HOUR, TIME),
+    (&["d", "day"], DAY, TIME),
+    (&["wk", "week"], 7.0 * DAY, TIME),
+    (&["fortnight"], 14.0 * DAY, TIME),
+    (&["yr", "year"], YEAR, TIME),
+    (&["month"], YEAR / 12.0, TIME),
+    (&["decade"], 10.0 * YEAR, TIME),
+    (&["century"], 100.0 * YEAR, TIME),
+    // Frequency
+    (&["Hz", "hertz"], 1.0, FREQUENCY),
+    (&["rpm"], 1.0 / 60.0, FREQUENCY),
+    // Speed and acceleration
+    (&["mph"], MILE / HOUR, SPEED),
+    (&["kph"], 1000.0 / HOUR, SPEED),
+    (&["kn", "knot"], NAUTICAL_MILE / HOUR, SPEED),
+    (&["fps"], FOOT, SPEED),
+    (&["c", "lightspeed"], 299_792_458.0, SPEED),
+    (&["gravity", "gee"], GRAVITY, ACCELERATION),
+    // Force and pressure
+    (&["N", "newton"], 1.0, FORCE),
+    (&["dyn", "dyne"], 1e-5, FORCE),
+    (&["lbf", "poundforce"], POUND * GRAVITY, FORCE),
+    (&["kgf", "kilogramforce"], GRAVITY, FORCE),
+    (&["Pa", "pascal"], 1.0, PRESSURE),
+    (&["bar"], 1e5, PRESSURE),
+    (&["atm", "atmosphere"], 101_325.0, PRESSURE),
+    (&["psi"], POUND * GRAVITY / (INCH * INCH), PRESSURE),
+    (&["torr"], 101_325.0 / 760.0, PRESSURE),
+    (&["mmHg"], 133.322_387_415, PRESSURE),
+    // Energy and power
+    (&["J", "joule"], 1.0, ENERGY),
+    (&["erg"], 1e-7, ENERGY),
+    (&["cal", "calorie"], CALORIE, ENERGY),
+    (&["Cal", "kcal"], 1000.0 * CALORIE, ENERGY),
+    (&["eV", "electronvolt"], 1.602_176_634e-19, ENERGY),
+    (&["Wh"], HOUR, ENERGY),
+    (&["BTU", "btu"], 1_055.055_852_62, ENERGY),
+    (&["tnt", "tonTNT"], 1e9 * CALORIE, ENERGY),
+    (&["W", "watt"], 1.0, POWER),
+    (&["hp", "horsepower"], 550.0 * FOOT * POUND * GRAVITY, POWER),
+    // Electricity
+    (&["A", "amp", "ampere"], 1.0, CURRENT),
+    (&["C", "coulomb"], 1.0, CHARGE),
+    (&["V", "volt"], 1.0, VOLTAGE),
+    (&["ohm"], 1.0, RESISTANCE),
+    // Everything else
+    (&["K", "kelvin"], 1.0, TEMPERATURE),
+    (&["mol", "mole"], 1.0, AMOUNT),
+    (&["cd", "candela"], 1.0, LUMINOSITY),
+    (&["rad", "radian"], 1.0, NONE),
+    (&["deg", "degree"], std::f64::consts::PI / 180.0, NONE),
+    (&["arcmin", "arcminute"], std::f64::consts::PI / 10_800.0, NONE),
+    (&["arcsec", "arcsecond"], std::f64::consts::PI / 648_000.0, NONE),
+    (&["pi"], std::f64::consts::PI, NONE),
+    (&["percent"], 0.01, NONE),
+    (&["dozen"], 12.0, NONE),
+    (&["gross"], 144.0, NONE),
+];
+
+/// SI prefixes, which may be used with any unit.
+const PREFIXES: &[(&[&str], f64)] = &[
+    (&["Y", "yotta"], 1e24),
+    (&["Z", "zetta"], 1e21),
+    (&["E", "exa"], 1e18),
+    (&["P", "peta"], 1e15),
+    (&["T", "tera"], 1e12),
+    (&["G", "giga"], 1e9),
+    (&["M", "mega"], 1e6),
+    (&["k", "kilo"], 1e3),
+    (&["h", "hecto"], 1e2),
+    (&["da", "deka", "deca"], 1e1),
+    (&["d", "deci"], 1e-1),
+    (&["c", "centi"], 1e-2),
+    (&["m", "milli"], 1e-3),
+    (&["u", "µ", "micro"], 1e-6),
+    (&["n", "nano"], 1e-9),
+    (&["p", "pico"], 1e-12),
+    (&["f", "femto"], 1e-15),
+    (&["a", "atto"], 1e-18),
+    (&["z", "zepto"], 1e-21),
+    (&["y", "yocto"], 1e-24),
+];
+
+lazy_static! {
+    static ref TABLE: HashMap<&'static str, Quantity> = UNITS
+        .iter()
+        .flat_map(|(names, value, dims)| names.iter().map(move |&name| (name, Quantity { value: *value, dims: *dims })))
+        .collect();
+}
+
+/// Parse and evaluate an expression of numbers and units, such as `3 ft + 2 in`.
+pub fn parse(input: &str) -> Result<Quantity, UnitError> {
+    let mut parser = Parser { rest: input, token: None, spaced: false };
+    parser.advance()?;
+
+    let quantity = parser.expr()?;
+
+    match parser.token {
+        None => Ok(quantity),
+        Some(token) => Err(UnitError::Unexpected(token.to_string())),
+    }
+}
+
+/// Convert an expression into a multiple of some other unit, e.g. `10 km` into `mi`.
+pub fn convert(from: &str, to: &str) -> Result<f64, UnitError> {
+    parse(from)?.in_units(&parse(to)?)
+}
+
+/// Look up a unit by name, with any SI prefix or plural ending.
+fn unit(name: &str) -> Option<Quantity> {
+    let base = |name: &str| {
+        TABLE.get(name).cloned().or_else(|| {
+            if name.len() > 2 && name.ends_with('s') {
+                let singular = TABLE.get(&name[..name.len() - 1]);
+                let plain = if name.ends_with("es") { TABLE.get(&name[..name.len() - 2]) } else { None };
+                singular.or(plain).cloned()
+            } else {
+                None
+            }
+        })
+    };
+
+    let prefixed = || {
+        PREFIXES.iter().find_map(|(prefixes, scale)| {
+            prefixes
+                .iter()
+                .filter(|p| name.len() > p.len() && name.starts_with(*p))
+                .find_map(|p| base(&name[p.len()..]))
+                .map(|q| Quantity { value: q.value * scale, dims: q.dims })
+        })
+    };
+
+    base(name)
+        .or_else(prefixed)
+        .or_else(|| if name.len() > 2 { base(&name.to_lowercase()) } else { None })
+}
+
+/// A number, in SI base units of some dimension.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    dims: Dims,
+}
+
+impl Quantity {
+    fn number(value: f64) -> Self {
+        Quantity { value, dims: NONE }
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.dims == NONE
+    }
+
+    /// This quantity as a multiple of another of the same dimension.
+    pub fn in_units(&self, unit: &Quantity) -> Result<f64, UnitError> {
+        if self.dims == unit.dims {
+            Ok(Quantity::number(self.value / unit.value).finite()?.value)
+        } else {
+            Err(UnitError::Mismatch(self.units(), unit.units()))
+        }
+    }
+
+    /// Fail if arithmetic has left the value infinite or undefined.
+    fn finite(self) -> Result<Quantity, UnitError> {
+        if self.value.is_finite() {
+            Ok(self)
+        } else {
+            Err(UnitError::NotFinite)
+        }
+    }
+
+    fn mul(self, rhs: Quantity) -> Result<Quantity, UnitError> {
+        let mut dims = self.dims;
+
+        for (d, r) in dims.iter_mut().zip(&rhs.dims) {
+            *d = d.checked_add(*r).ok_or(UnitError::Exponent)?;
+        }
+
+        Quantity { value: self.value * rhs.value, dims }.finite()
+    }
+
+    fn div(self, rhs: Quantity) -> Result<Quantity, UnitError> {
+        let mut dims = self.dims;
+
+        for (d, r) in dims.iter_mut().zip(&rhs.dims) {
+            *d = d.checked_sub(*r).ok_or(UnitError::Exponent)?;
+        }
+
+        Quantity { value: self.value / rhs.value, dims }.finite()
+    }
+
+    fn add(self, rhs: Quantity, sign: f64) -> Result<Quantity, UnitError> {
+        if self.dims == rhs.dims {
+            Quantity { value: self.value + sign * rhs.value, dims: self.dims }.finite()
+        } else {
+            Err(UnitError::Mismatch(self.units(), rhs.units()))
+        }
+    }
+
+    fn pow(self, rhs: Quantity) -> Result<Quantity, UnitError> {
+        if !rhs.is_dimensionless() {
+            return Err(UnitError::Exponent);
+        }
+
+        if self.is_dimensionless() {
+            return Quantity::number(self.value.powf(rhs.value)).finite();
+        }
+
+        #[allow(clippy::float_cmp)] // Only whole powers of units make sense.
+        let whole = rhs.value.trunc() == rhs.value && rhs.value.abs() <= 32.0;
+
+        if !whole {
+            return Err(UnitError::Exponent);
+        }
+
+        let mut dims = self.dims;
+
+        for d in dims.iter_mut() {
+            *d = d.checked_mul(rhs.value as i8).ok_or(UnitError::Exponent)?;
+        }
+
+        Quantity { value: self.value.powi(rhs.value as i32), dims }.finite()
+    }
+
+    /// The base units of the quantity, e.g. `kg m^2 / s^2`.
+    fn units(&self) -> String {
+        let part = |(i, symbol): &(usize, &str), sign: i16| {
+            match i16::from(self.dims[*i]) * sign {
+                1 => Some(symbol.to_string()),
+                p if p > 1 => Some(format!("{}^{}", symbol, p)),
+                _ => None,
+            }
+        };
+
+        let numerator = BASE.iter().filter_map(|b| part(b, 1)).collect::<Vec<_>>();
+        let denominator = BASE.iter().filter_map(|b| part(b, -1)).collect::<Vec<_>>();
+
+        match (numerator.is_empty(), denominator.is_empty()) {
+            (_, true) => numerator.join(" "),
+            (true, false) => format!("/ {}", denominator.join(" ")),
+            (false, false) => format!("{} / {}", numerator.join(" "), denominator.join(" ")),
+        }
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_dimensionless() {
+            write!(f, "{}", number(self.value))
+        } else {
+            write!(f, "{} {}", number(self.value), self.units())
+        }
+    }
+}
+
+/// Format a number to eight significant figures, like C's `%.8g`.
+pub fn number(value: f64) -> String {
+    const DIGITS: i32 = 8;
+
+    fn trim(s: &str) -> &str {
+        if s.contains('.') { s.trim_end_matches('0').trim_end_matches('.') } else { s }
+    }
+
+    if value == 0.0 || !value.is_finite() {
+        return value.to_string();
+    }
+
+    let scientific = format!("{:.*e}", (DIGITS - 1) as usize, value);
+    let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap_or_default());
+    let exponent = exponent[1..].parse::<i32>().unwrap_or_default();
+
+    if exponent < -4 || exponent >= DIGITS {
+        format!("{}e{}{:02}", trim(mantissa), if exponent < 0 { '-' } else { '+' }, exponent.abs())
+    } else {
+        trim(&format!("{:.*}", (DIGITS - 1 - exponent) as usize, value)).to_string()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(f64),
+    Name(String),
+    Op(char),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Num(n) => write!(f, "{}", n),
+            Token::Name(name) => write!(f, "{}", name),
+            Token::Op(op) => write!(f, "{}", op),
+        }
+    }
+}
+
+/// A recursive descent parser over units expressions, following GNU units in
+/// giving multiplication by juxtaposition precedence over `/`, so `J/mol K` is
+/// joules per mole-kelvin, and `|` the highest precedence for dividing numbers.
+struct Parser<'a> {
+    rest: &'a str,
+    token: Option<Token>,
+    spaced: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn advance(&mut self) -> Result<Option<Token>, UnitError> {
+        let trimmed = self.rest.trim_start();
+        self.spaced = trimmed.len() < self.rest.len();
+        self.rest = trimmed;
+
+        let mut chars = self.rest.char_indices();
+
+        let (next, len) = match chars.next() {
+            None => (None, 0),
+            Some((_, c)) if c.is_ascii_digit() || c == '.' => {
+                let bytes = self.rest.as_bytes();
+                let mut len = self.rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(self.rest.len());
+
+                // Only take an `e` as an exponent if digits follow, so `5erg` is five ergs.
+                if len < bytes.len() && (bytes[len] == b'e' || bytes[len] == b'E') {
+                    let mut end = len + 1;
+
+                    if end < bytes.len() && (bytes[end] == b'-' || bytes[end] == b'+') {
+                        end += 1;
+                    }
+
+                    let digits = bytes[end..].iter().take_while(|b| b.is_ascii_digit()).count();
+
+                    if digits > 0 {
+                        len = end + digits;
+                    }
+                }
+
+                let number = &self.rest[..len];
+                let value = number.parse().map_err(|_| UnitError::Number(number.to_string()))?;
+                (Some(Token::Num(Quantity::number(value).finite()?.value)), len)
+            }
+            Some((_, c)) if c.is_alphabetic() || c == '_' => {
+                let len = self.rest.find(|c: char| !c.is_alphabetic() && c != '_').unwrap_or(self.rest.len());
+
+                match &self.rest[..len] {
+                    "per" => (Some(Token::Op('/')), len),
+                    name => (Some(Token::Name(name.to_string())), len),
+                }
+            }
+            Some((_, '*')) if self.rest.starts_with("**") => (Some(Token::Op('^')), 2),
+            Some((_, c)) if "+-*/^|()×÷".contains(c) => match c {
+                '×' => (Some(Token::Op('*')), c.len_utf8()),
+                '÷' => (Some(Token::Op('/')), c.len_utf8()),
+                c => (Some(Token::Op(c)), 1),
+            },
+            Some((_, c)) => return Err(UnitError::Unexpected(c.to_string())),
+        };
+
+        self.rest = &self.rest[len..];
+        Ok(std::mem::replace(&mut self.token, next))
+    }
+
+    fn eat(&mut self, op: char) -> Result<bool, UnitError> {
+        if self.token == Some(Token::Op(op)) {
+            self.advance()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    // expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<Quantity, UnitError> {
+        let mut lhs = self.term()?;
+
+        loop {
+            if self.eat('+')? {
+                lhs = lhs.add(self.term()?, 1.0)?;
+            } else if self.eat('-')? {
+                lhs = lhs.add(self.term()?, -1.0)?;
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    // term := product (('*' | '/') product)*
+    fn term(&mut self) -> Result<Quantity, UnitError> {
+        let mut lhs = self.product()?;
+
+        loop {
+            if self.eat('*')? {
+                lhs = lhs.mul(self.product()?)?;
+            } else if self.eat('/')? {
+                lhs = lhs.div(self.product()?)?;
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    // product := unary unary*
+    fn product(&mut self) -> Result<Quantity, UnitError> {
+        let mut lhs = self.unary()?;
+
+        while let Some(Token::Num(_)) | Some(Token::Name(_)) | Some(Token::Op('(')) = self.token {
+            lhs = lhs.mul(self.unary()?)?;
+        }
+
+        Ok(lhs)
+    }
+
+    // unary := '-' unary | power
+    fn unary(&mut self) -> Result<Quantity, UnitError> {
+        if self.eat('-')? {
+            let q = self.unary()?;
+            Ok(Quantity { value: -q.value, ..q })
+        } else {
+            self.power()
+        }
+    }
+
+    // power := primary ('^' unary)?
+    fn power(&mut self) -> Result<Quantity, UnitError> {
+        let base = self.primary()?;
+
+        if self.eat('^')? {
+            base.pow(self.unary()?)
+        } else {
+            Ok(base)
+        }
+    }
+
+    // primary := number ('|' number)? | unit digits? | '(' expr ')'
+    fn primary(&mut self) -> Result<Quantity, UnitError> {
+        match self.advance()? {
+            Some(Token::Num(n)) => {
+                if self.eat('|')? {
+                    match self.advance()? {
+                        Some(Token::Num(d)) => Quantity::number(n / d).finite(),
+                        Some(token) => Err(UnitError::Unexpected(token.to_string())),
+                        None => Err(UnitError::Incomplete),
+                    }
+                } else {
+                    Ok(Quantity::number(n))
+                }
+            }
+            Some(Token::Name(name)) => {
+                let q = unit(&name).ok_or(UnitError::Unknown(name))?;
+
+                // A power written straight after the unit, like `ft2`.
+                match self.token {
+                    Some(Token::Num(p)) if !self.spaced => {
+                        self.advance()?;
+                        q.pow(Quantity::number(p))
+                    }
+                    _ => Ok(q),
+                }
+            }
+            Some(Token::Op('(')) => {
+                let q = self.expr()?;
+
+                if self.eat(')')? {
+                    Ok(q)
+                } else {
+                    Err(UnitError::Unclosed)
+                }
+            }
+            Some(token) => Err(UnitError::Unexpected(token.to_string())),
+            None => Err(UnitError::Incomplete),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum UnitError {
+    Unknown(String),
+    Mismatch(String, String),
+    Number(String),
+    Unexpected(String),
+    Unclosed,
+    Incomplete,
+    Exponent,
+    NotFinite,
+}
+
+impl fmt::Display for UnitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitError::Unknown(name) => write!(f, "I don't know the unit `{}`", name),
+            UnitError::Mismatch(a, b) if a.is_empty() => write!(f, "a number can't be converted to `{}`", b),
+            UnitError::Mismatch(a, b) if b.is_empty() => write!(f, "`{}` can't be converted to a number", a),
+            UnitError::Mismatch(a, b) => write!(f, "`{}` can't be converted to `{}`", a, b),
+            UnitError::Number(n) => write!(f, "`{}` isn't a number", n),
+            UnitError::Unexpected(token) => write!(f, "I didn't expect `{}`", token),
+            UnitError::Unclosed => write!(f, "there's an unclosed `(`"),
+            UnitError::Incomplete => write!(f, "the expression ended unexpectedly"),
+            UnitError::Exponent => write!(f, "units can only be raised to small whole powers"),
+            UnitError::NotFinite => write!(f, "the result isn't a finite number"),
+        }
+    }
+}
+
+impl Error for UnitError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-9 * a.abs().max(b.abs())
+    }
+
+    fn yards(s: &str) -> f64 {
+        convert(s, "yd").unwrap()
+    }
+
+    #[test]
+    fn conversions() {
+        assert!(close(yards("1 mi"), 1760.0));
+        assert!(close(yards("100 m"), 109.361_329_833_770_78));
+        assert!(close(yards("5 ft + 3 ft"), 8.0 / 3.0));
+        assert!(close(yards("2 miles"), 3520.0));
+        assert!(close(yards("3 kilometers"), 3_280.839_895_013_123));
+        assert!(close(convert("60 mph", "kph").unwrap(), 96.560_64));
+        assert!(close(convert("1 gallon", "l").unwrap(), 3.785_411_784));
+        assert!(close(convert("1 ukgallon", "l").unwrap(), 4.546_09));
+        assert!(close(convert("2 imperialpints", "floz").unwrap(), 38.430_397_616_155_36));
+        assert!(close(convert("1 impgal", "impfloz").unwrap(), 160.0));
+        assert!(close(convert("1 lb", "g").unwrap(), 453.592_37));
+        assert!(close(convert("1 kWh", "MJ").unwrap(), 3.6));
+        assert!(close(convert("1 ft2", "in^2").unwrap(), 144.0));
+        assert!(close(convert("90 deg", "rad").unwrap(), std::f64::consts::FRAC_PI_2));
+        assert!(close(convert("1 hp", "W").unwrap(), 745.699_871_582_270_2));
+        assert!(close(convert("1 day", "ms").unwrap(), 86_400_000.0));
+    }
+
+    #[test]
+    fn precedence() {
+        assert!(close(parse("1/2 m").unwrap().value, 0.5));
+        assert!(close(convert("1|2 m", "m").unwrap(), 0.5));
+        assert!(close(convert("J/mol K", "J / (mol K)").unwrap(), 1.0));
+        assert!(close(parse("2^3^2").unwrap().value, 512.0));
+        assert!(close(parse("-2^2").unwrap().value, -4.0));
+        assert!(close(parse("1.5e3").unwrap().value, 1500.0));
+        assert!(close(parse("2 * (3 + 4)").unwrap().value, 14.0));
+        assert!(close(convert("10 m per s", "m/s").unwrap(), 10.0));
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(parse("1 ft").unwrap().to_string(), "0.3048 m");
+        assert_eq!(parse("1 J").unwrap().to_string(), "1 kg m^2 / s^2");
+        assert_eq!(parse("1 Hz").unwrap().to_string(), "1 / s");
+        assert_eq!(number(1.0 / 3.0), "0.33333333");
+        assert_eq!(number(123_456_789.0), "1.2345679e+08");
+        assert_eq!(number(0.000_012_5), "1.25e-05");
+        assert_eq!(number(-42.0), "-42");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("3 furlongz"), Err(UnitError::Unknown("furlongz".into())));
+        assert_eq!(convert("3 ft", "lb").unwrap_err(), UnitError::Mismatch("m".into(), "kg".into()));
+        assert_eq!(parse("1 m + 1 s").unwrap_err(), UnitError::Mismatch("m".into(), "s".into()));
+        assert_eq!(parse("(1 m"), Err(UnitError::Unclosed));
+        assert_eq!(parse("m^0.5"), Err(UnitError::Exponent));
+        assert_eq!(parse("(m^32)^4"), Err(UnitError::Exponent));
+        assert_eq!(parse("m^32 m^32 m^32 m^32"), Err(UnitError::Exponent));
+        assert!(convert("m^-32 m^-32 m^-32 m^-32", "s").is_err());
+        assert_eq!(parse("0|0"), Err(UnitError::NotFinite));
+        assert_eq!(parse("1e400"), Err(UnitError::NotFinite));
+        assert_eq!(parse("1 / 0"), Err(UnitError::NotFinite));
+        assert_eq!(convert("1 m", "0 m"), Err(UnitError::NotFinite));
+        assert!(close(parse("5erg").unwrap().value, 5e-7));
+        assert_eq!(parse("3 *"), Err(UnitError::Incomplete));
+        assert_eq!(parse("3 m)"), Err(UnitError::Unexpected(")".into())));
+    }
+}
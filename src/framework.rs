@@ -36,6 +36,7 @@ pub fn new(owner_id: UserId, bot_id: UserId) -> StandardFramework {
         .group(&crate::modules::chartrack::TRACKER_GROUP)
         .group(&crate::modules::dice::DICE_GROUP)
         .group(&crate::modules::gurps::GURPS_GROUP)
+        .group(&crate::modules::macros::MACROS_GROUP)
         .group(&crate::modules::memory::MEMORY_GROUP)
         .group(&crate::modules::random::RANDOM_GROUP)
         .group(&crate::modules::toys::TOYS_GROUP)
@@ -75,6 +76,34 @@ fn dynamic_prefix(ctx: &mut Context, msg: &Message) -> Option<String> {
     channel_prefix.or_else(guild_prefix).cloned()
 }
 
+/// The prefix or mention that a message starts with, if any.
+fn command_prefix(ctx: &mut Context, msg: &Message, bot_id: UserId) -> Option<String> {
+    let content = msg.content.trim_start();
+    let mentions = vec![format!("<@{}>", bot_id.0), format!("<@!{}>", bot_id.0)];
+
+    dynamic_prefix(ctx, msg)
+        .into_iter()
+        .chain(mentions)
+        .find(|p| content.starts_with(p.as_str()))
+}
+
+/// Whether a message will be dispatched as a command, by a prefix or a mention.
+pub fn is_command(ctx: &mut Context, msg: &Message, bot_id: UserId) -> bool {
+    command_prefix(ctx, msg, bot_id).is_some()
+}
+
+/// The arguments following a command's prefix and name.
+fn command_args<'a>(ctx: &mut Context, msg: &'a Message, name: &str) -> &'a str {
+    let bot_id = serenity::utils::with_cache(&ctx, |cache| cache.user.id);
+    let prefix = command_prefix(ctx, msg, bot_id).map_or(0, |p| p.len());
+    let rest = msg.content.trim_start()[prefix..].trim_start();
+
+    match rest.get(..name.len()) {
+        Some(word) if word.eq_ignore_ascii_case(name) => &rest[name.len()..],
+        _ => "",
+    }
+}
+
 #[help]
 fn help(
     context: &mut Context,
@@ -112,7 +141,10 @@ fn on_dispatch_error(ctx: &mut Context, msg: &Message, err: DispatchError) {
 
 fn unrecognised_command(ctx: &mut Context, msg: &Message, name: &str) {
     match Memory::get_bareword(name) {
-        Err(diesel::result::Error::NotFound) => (),
+        Err(diesel::result::Error::NotFound) => {
+            let args = command_args(ctx, msg, name);
+            crate::modules::macros::handle_bareword(ctx, msg, name, args);
+        }
         Err(err) => log::warn!("[{}:{}] {:?}", line!(), column!(), err),
         Ok(def) => {
             if def.embedded {
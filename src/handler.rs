@@ -1,5 +1,4 @@
 use crate::db::BotInfo;
-use crate::model::DiceCache;
 use crate::logger::MessageLogger;
 use serenity::model::prelude::*;
 use serenity::prelude::*;
@@ -45,8 +44,9 @@ impl EventHandler for Handler {
     fn reaction_remove(&self, _ctx: Context, _removed_reaction: Reaction) {}
     fn reaction_remove_all(&self, _ctx: Context, _channel_id: ChannelId, _removed_from_message_id: MessageId) {}
 
-    fn message(&self, ctx: Context, msg: Message) {
+    fn message(&self, mut ctx: Context, msg: Message) {
         msg.log(&ctx);
+        crate::modules::dice::handle_inline(&mut ctx, &msg);
     }
 
     fn ready(&self, ctx: Context, ready: Ready) {
@@ -75,18 +75,7 @@ impl EventHandler for Handler {
         match re.emoji {
             // Reroll dice.
             ReactionType::Unicode(ref x) if x == "🎲" => {
-                let cached = {
-                    let mut data = ctx.data.write();
-                    let cache = data.entry::<DiceCache>().or_insert_with(Default::default);
-                    cache.remove(&re.message_id)
-                };
-
-                if let Some(expr) = cached {
-                    err_log!(re.channel_id.delete_reaction(&ctx, re.message_id, None, '🎲'));
-                    crate::modules::dice::handle_roll(&ctx, re.channel_id, re.user_id, &expr);
-                } else {
-                    log::info!("Die roll is not in message cache.");
-                };
+                crate::modules::dice::handle_reroll(&ctx, &re);
             }
 
             // Delete message.
@@ -18,9 +18,9 @@ impl TypeMapKey for SerenityShardManager {
     type Value = Arc<Mutex<ShardManager>>;
 }
 
-pub struct DiceCache;
-impl TypeMapKey for DiceCache {
-    type Value = HashMap<MessageId, String>;
+pub struct DiceSettingsCache;
+impl TypeMapKey for DiceSettingsCache {
+    type Value = HashMap<i64, DiceSettings>;
 }
 
 pub struct PrefixCache;
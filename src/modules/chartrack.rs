@@ -225,34 +225,7 @@ fn add(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
     let value   = args.single::<i32>()?;
     let comment = args.rest();
 
-    let result = || -> Result<Attribute, TrackError> {
-        let ch = DB::get_character_by_pair(&who, msg.channel_id.into())?;
-        denied(&ch, msg.author.id)?;
-
-        let mut attr = match DB::get_attribute(&name, ch.pin) {
-            Err(NotFound) => Err(TrackError::Exists)?,
-            Err(error)    => Err(TrackError::Query(error))?,
-            Ok(attribute) => attribute,
-        };
-
-        attr.value += value;
-        DB::update_attribute(&attr)?;
-
-        update_pin(&ctx, &ch, &comment)?;
-
-        Ok(attr)
-    }();
-
-    match result {
-        Err(TrackError::Denied) => say!(ctx, msg, "Sorry, you're not allowed to edit {}.", who),
-        Err(TrackError::Exists) => say!(ctx, msg, "Sorry, I'm not tracking {} for {}.", name, who),
-        Err(TrackError::Query(NotFound)) => say!(ctx, msg, "Sorry, I'm not tracking {}.", who),
-        Err(TrackError::Query(error)) => Err(error)?,
-        Err(TrackError::Serenity(error)) => Err(error)?,
-        Ok(at) => say!(ctx, msg, "Set {} for {} to {}.", at.name, who, at.value),
-    }
-
-    Ok(())
+    adjust(ctx, msg, &who, &name, value, comment)
 }
 
 #[command]
@@ -266,20 +239,25 @@ fn sub(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
     let value   = args.single::<i32>()?;
     let comment = args.rest();
 
+    adjust(ctx, msg, &who, &name, -value, comment)
+}
+
+/// Adds to a character attribute on behalf of the author of a message.
+pub(crate) fn adjust(ctx: &Context, msg: &Message, who: &str, name: &str, value: i32, comment: &str) -> CommandResult {
     let result = || -> Result<Attribute, TrackError> {
-        let ch = DB::get_character_by_pair(&who, msg.channel_id.into())?;
+        let ch = DB::get_character_by_pair(who, msg.channel_id.into())?;
         denied(&ch, msg.author.id)?;
 
-        let mut attr= match DB::get_attribute(&name, ch.pin) {
+        let mut attr = match DB::get_attribute(name, ch.pin) {
             Err(NotFound) => Err(TrackError::Exists)?,
             Err(error)    => Err(TrackError::Query(error))?,
             Ok(attribute) => attribute,
         };
 
-        attr.value -= value;
+        attr.value += value;
         DB::update_attribute(&attr)?;
 
-        update_pin(&ctx, &ch, &comment)?;
+        update_pin(&ctx, &ch, comment)?;
 
         Ok(attr)
     }();
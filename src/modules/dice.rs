@@ -1,41 +1,807 @@
-use crate::ext::dice::DiceRoll;
-use crate::model::DiceCache;
+use chrono::{Duration, Utc};
+use diesel::result::OptionalExtension;
+use crate::db::DiceConfig as DB;
+use crate::db::{CharTrack, DiceCache, RollLog};
+use crate::db::model::{CachedRoll, DiceSettings, NewRollEntry, RollEntry};
+use crate::ext::dice::{self, Contest, Critical, DiceExpr, DiceRoll, GameSystem, Limits, RollError};
+use crate::model::DiceSettingsCache;
 use itertools::Itertools;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serenity::builder::CreateEmbed;
 use serenity::client::Context;
 use serenity::framework::standard::macros::{command, group};
 use serenity::framework::standard::{Args, CommandResult};
 use serenity::model::prelude::*;
+use serenity::prelude::Mutex;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{self, AtomicUsize};
 
-pub fn handle_roll(ctx: &Context, channel: ChannelId, user: UserId, input: &str) {
+lazy_static! {
+    /// Caps on the size of rolls, read once from the environment.
+    pub(crate) static ref LIMITS: Limits = Limits::from_env();
+
+    /// How long a roll can be rerolled for, from `DICE_CACHE_TTL` in hours.
+    static ref CACHE_TTL: Duration = Duration::hours(env_or("DICE_CACHE_TTL", 24 * 7));
+
+    /// The most rolls kept for rerolling, from `DICE_CACHE_SIZE`.
+    static ref CACHE_SIZE: i64 = env_or("DICE_CACHE_SIZE", 10_000);
+
+    /// A lock for each message being rerolled, so that rerolls of the same
+    /// message take turns instead of overwriting each other's results.
+    static ref REROLLING: Mutex<HashMap<MessageId, Arc<Mutex<()>>>> = Mutex::new(HashMap::new());
+
+    /// An inline roll in an ordinary message, like `[[3d6+2]]`.
+    static ref INLINE: Regex = Regex::new(r"\[\[([^\[\]]+)\]\]").unwrap();
+
+    /// A reference to a tracked character's attribute, like `@Bob.DX` or `@"Bob Smith".DX`.
+    static ref ATTRIBUTE: Regex = Regex::new(r#"@(?:"([^"]+)"|(\w+))\.(\w+)"#).unwrap();
+}
+
+fn env_or(key: &str, default: i64) -> i64 {
+    std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+/// Remember a roll so it can be rerolled, now and then evicting any expired rolls.
+fn cache_roll(message: MessageId, input: &str, roller: UserId, rerolls: i32) {
+    /// How many rolls are cached between evictions.
+    const EVICT_EVERY: usize = 100;
+    static CACHED: AtomicUsize = AtomicUsize::new(0);
+
+    let now = Utc::now();
+    let roll = CachedRoll {
+        message: message.into(),
+        input: input.to_string(),
+        timestamp: now,
+        roller: roller.into(),
+        rerolls,
+    };
+
+    err_log!(DiceCache::add(&roll));
+
+    // Lookups skip expired rolls anyway, so the cache need only be trimmed occasionally.
+    if CACHED.fetch_add(1, atomic::Ordering::Relaxed) % EVICT_EVERY == 0 {
+        err_log!(DiceCache::evict(now - *CACHE_TTL, *CACHE_SIZE));
+    }
+}
+
+/// Find a roll in the reroll cache, if it hasn't expired.
+fn cached_roll(message: MessageId) -> Option<CachedRoll> {
+    match DiceCache::get(message.into(), Utc::now() - *CACHE_TTL) {
+        Ok(roll) => Some(roll),
+        Err(diesel::result::Error::NotFound) => None,
+        Err(err) => {
+            log::warn!("[{}:{}] {:?}", line!(), column!(), err);
+            None
+        }
+    }
+}
+
+/// Whether only the original roller may reroll dice in a channel.
+fn own_rerolls(ctx: &Context, channel: ChannelId) -> bool {
+    let mut data = ctx.data.write();
+    let cache = data
+        .entry::<DiceSettingsCache>()
+        .or_insert_with(|| DB::get().unwrap());
+
+    cache
+        .get(&{ let i:i64 = channel.into(); -i})
+        .and_then(|s| s.own_rerolls)
+        .unwrap_or(false)
+}
+
+/// The guild a channel belongs to, if it's a cached guild channel.
+fn guild_of(ctx: &Context, channel: ChannelId) -> Option<GuildId> {
+    channel
+        .to_channel_cached(&ctx)
+        .and_then(Channel::guild)
+        .map(|g| g.read().guild_id)
+}
+
+/// The game system for a channel, falling back to its guild's and then the default.
+fn game_system(ctx: &Context, channel: ChannelId) -> GameSystem {
+    let guild = guild_of(ctx, channel);
+
+    let mut data = ctx.data.write();
+    let cache = data
+        .entry::<DiceSettingsCache>()
+        .or_insert_with(|| DB::get().unwrap());
+
+    let channel_system = cache
+        .get(&{ let i:i64 = channel.into(); -i})
+        .and_then(|s| s.system)
+        .map(GameSystem::from);
+    let guild_system = || {
+        guild.and_then(|i| cache.get(&i.into()).and_then(|s| s.system)).map(GameSystem::from)
+    };
+
+    channel_system.or_else(guild_system).unwrap_or_default()
+}
+
+/// Whether a user's rolls are shown as embeds, falling back to their guild's setting.
+fn embed_mode(ctx: &Context, channel: ChannelId, user: UserId) -> bool {
+    let guild = guild_of(ctx, channel);
+
+    let mut data = ctx.data.write();
+    let cache = data
+        .entry::<DiceSettingsCache>()
+        .or_insert_with(|| DB::get().unwrap());
+
+    let user_embed = cache
+        .get(&user.into())
+        .and_then(|s| s.embed);
+    let guild_embed = || {
+        guild.and_then(|i| cache.get(&i.into()).and_then(|s| s.embed))
+    };
+
+    user_embed.or_else(guild_embed).unwrap_or(false)
+}
+
+/// Whether inline rolls are answered in a channel.
+fn inline_rolls(ctx: &Context, channel: ChannelId) -> bool {
+    let mut data = ctx.data.write();
+    let cache = data
+        .entry::<DiceSettingsCache>()
+        .or_insert_with(|| DB::get().unwrap());
+
+    cache
+        .get(&{ let i:i64 = channel.into(); -i})
+        .and_then(|s| s.inline)
+        .unwrap_or(false)
+}
+
+/// Whether the bot may post embeds in a channel.
+fn can_embed(ctx: &Context, channel: ChannelId) -> bool {
+    let bot_id = serenity::utils::with_cache(&ctx, |cache| cache.user.id);
+
+    match channel.to_channel_cached(&ctx) {
+        Some(Channel::Guild(ch)) => ch.read().permissions_for_user(&ctx, bot_id).map_or(false, |p| p.embed_links()),
+        _ => true,
+    }
+}
+
+/// Replace each attribute reference in an expression with its value for the
+/// character tracked in the channel, noting each substitution made.
+fn substitute(s: &str, channel: ChannelId, notes: &mut Vec<String>) -> Result<String, String> {
+    let mut error = None;
+
+    let substituted = ATTRIBUTE.replace_all(s, |cap: &Captures<'_>| {
+        let reference = cap.get(0).unwrap();
+        let who = cap.get(1).or_else(|| cap.get(2)).map_or("", |m| m.as_str());
+        let name = &cap[3];
+
+        let value = match CharTrack::get_character_by_pair(who, channel.into()) {
+            Err(diesel::result::Error::NotFound) => Err(format!("I'm not tracking {}", who)),
+            Err(err) => Err(err.to_string()),
+            Ok(ch) => match CharTrack::get_attribute(name, ch.pin) {
+                Err(diesel::result::Error::NotFound) => Err(format!("{} has no {}", who, name)),
+                Err(err) => Err(err.to_string()),
+                Ok(at) => Ok(at.value),
+            },
+        };
+
+        match value {
+            Ok(value) => {
+                let note = format!("{}.{} = {}", who, name, value);
+
+                if !notes.contains(&note) {
+                    notes.push(note);
+                }
+
+                if value < 0 { format!("({})", value) } else { value.to_string() }
+            }
+            Err(message) => {
+                if error.is_none() {
+                    let offset = s[..reference.start()].chars().count();
+                    let width = reference.as_str().chars().count();
+                    error = Some(format!("{}\n{}{} {}", s, " ".repeat(offset), "^".repeat(width), message));
+                }
+
+                String::new()
+            }
+        }
+    });
+
+    match error {
+        Some(error) => Err(error),
+        None => Ok(substituted.into_owned()),
+    }
+}
+
+/// Check that each expression in the input can be read, without rolling it.
+pub fn check_input(input: &str) -> Result<(), String> {
+    let expr = input.splitn(2, '#').next().unwrap_or_default();
+
+    for s in expr.split(|c| c == ';' || c == '\n').map(str::trim).filter(|s| !s.is_empty()) {
+        let s = ATTRIBUTE.replace_all(s, "0");
+
+        if let Err(err) = s.parse::<DiceExpr>() {
+            return Err(err.snippet(&s));
+        }
+    }
+
+    Ok(())
+}
+
+/// The results of rolling every expression in a message.
+struct Rolled {
+    comment: Option<String>,
+    /// Any attribute values substituted into the expressions.
+    notes: Vec<String>,
+    /// Each expression rolled, with its results.
+    rolls: Vec<(String, DiceRoll)>,
+    /// An underlined snippet for each expression that couldn't be rolled.
+    errors: String,
+    /// The expressions read, with attributes substituted, which replay the roll from its seed.
+    input: String,
+    seed: u64,
+}
+
+impl Rolled {
+    fn comment(&self) -> String {
+        let comment = self.comment.as_ref().map(|c| format!(" _{}_", c)).unwrap_or_default();
+        comment + &self.comment_notes()
+    }
+
+    fn comment_notes(&self) -> String {
+        if self.notes.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", self.notes.join(", "))
+        }
+    }
+
+    fn results(&self) -> String {
+        self.rolls.iter().map(|(_, roll)| roll.to_string()).join("\n")
+    }
+
+    fn errors(&self) -> String {
+        if self.errors.is_empty() {
+            String::new()
+        } else {
+            format!("Couldn't read:\n```{}```", self.errors)
+        }
+    }
+}
+
+/// Roll each expression in the input from the given seed, substituting the
+/// attributes of characters tracked in the channel.
+fn roll_input(input: &str, channel: ChannelId, system: GameSystem, seed: u64) -> Rolled {
     let (expr, comment) = {
         if let Some((expr, comment)) = input.splitn(2, '#').collect_tuple() {
-            (expr, format!(" _{}_", comment.trim()))
+            (expr, Some(comment.trim().to_string()))
         } else {
-            (input, String::new())
+            (input, None)
         }
     };
 
-    let roll = expr
-        .split(|c| c == ';' || c == '\n')
-        .map(|s| if s.is_empty() { "3d6" } else { s })
-        .filter_map(|s| s.parse::<DiceRoll>().ok())
-        .map(|r| r.to_string())
-        .join("\n");
+    let mut rng = dice::seeded(seed);
+    let mut rolls = Vec::new();
+    let mut errors = Vec::new();
+    let mut notes = Vec::new();
+    let mut replay = Vec::new();
+    let mut length = 0;
+
+    for s in expr.split(|c| c == ';' || c == '\n').map(str::trim) {
+        let s = if s.is_empty() { "3d6" } else { s };
+
+        let substituted = match substitute(s, channel, &mut notes) {
+            Ok(substituted) => substituted,
+            Err(err) => {
+                push_error(&mut errors, &mut length, err);
+                continue;
+            }
+        };
+
+        let expr = match substituted.parse::<DiceExpr>() {
+            Ok(expr) => expr,
+            Err(err) => {
+                push_error(&mut errors, &mut length, err.snippet(&substituted));
+                continue;
+            }
+        };
+
+        replay.push(substituted.clone());
+
+        let roll = expr
+            .roll(&mut rng, &LIMITS)
+            .map(|roll| roll.with_system(system))
+            .and_then(|roll| match roll.render(LIMITS.output.saturating_sub(length)) {
+                Some(text) => {
+                    length += text.len() + 1;
+                    Ok(roll)
+                }
+                None => Err(RollError::TooLong(LIMITS.output)),
+            });
+
+        match roll {
+            Ok(roll) => rolls.push((s.to_string(), roll)),
+            Err(err) => {
+                let snippet = format!("{}\n{} {}", s, "^".repeat(s.chars().count()), err);
+                push_error(&mut errors, &mut length, snippet);
+            }
+        }
+    }
+
+    let mut input = replay.join("; ");
+
+    if let Some(comment) = &comment {
+        input = format!("{} # {}", input, comment);
+    }
+
+    Rolled { comment, notes, rolls, errors: errors.join("\n\n"), input, seed }
+}
+
+/// Add an error, shortened to whatever is left of the output limit shared with
+/// the results, and dropped once nothing is left.
+fn push_error(errors: &mut Vec<String>, length: &mut usize, error: String) {
+    let left = LIMITS.output.saturating_sub(*length);
 
-    let content = format!("**{} rolled:**{}\n```{}```", user.mention(), comment, roll);
+    if left > 0 {
+        let error = truncate(&error, left);
+        *length += error.len() + 2;
+        errors.push(error);
+    }
+}
 
-    match channel.send_message(&ctx, |m| m.content(content).reactions(Some('🎲'))) {
+/// Discord's limits on an embed's title and on each field's name and value,
+/// in characters. Descriptions are held well under Discord's limit, leaving
+/// room for the fields within its limit on the whole embed.
+const MAX_TITLE: usize = 256;
+const MAX_DESCRIPTION: usize = 512;
+const MAX_NAME: usize = 256;
+const MAX_VALUE: usize = 1024;
+
+/// Shorten text to at most `max` characters, marking any cut with an ellipsis.
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_string()
+    } else {
+        s.chars().take(max.saturating_sub(1)).chain(Some('…')).collect()
+    }
+}
+
+/// A field for each roll and any errors, if they fit within Discord's limits on embeds.
+fn embed_fields(rolled: &Rolled) -> Option<Vec<(String, String)>> {
+    const MAX_FIELDS: usize = 25;
+    const MAX_TOTAL: usize = 5000;
+
+    let mut fields = Vec::new();
+
+    for (expression, roll) in &rolled.rolls {
+        let count = roll.rolls().len();
+
+        for (i, r) in roll.rolls().iter().enumerate() {
+            let name = if count > 1 { format!("{} (#{})", expression, i + 1) } else { expression.clone() };
+            fields.push((truncate(&name, MAX_NAME), format!("**{}**\n{}", r.summary(), r.markdown())));
+        }
+    }
+
+    if !rolled.errors.is_empty() {
+        let errors = truncate(&rolled.errors, MAX_VALUE - 6);
+        fields.push((String::from("Couldn't read"), format!("```{}```", errors)));
+    }
+
+    // Results that don't fit are sent as plain text instead, rather than cut short.
+    let length = |s: &String| s.chars().count();
+    let total = fields.iter().map(|(name, value)| length(name) + length(value)).sum::<usize>();
+    let fits = fields.len() <= MAX_FIELDS
+        && total <= MAX_TOTAL
+        && fields.iter().all(|(_, value)| length(value) <= MAX_VALUE);
+
+    if fits { Some(fields) } else { None }
+}
+
+/// Fill in an embed with the fields for a set of rolls, beneath a heading such as `@user rolled`.
+fn build_embed<'a>(e: &'a mut CreateEmbed, heading: &str, rolled: &Rolled, fields: Vec<(String, String)>) -> &'a mut CreateEmbed {
+    let description = format!("{}{}", heading, rolled.comment_notes());
+
+    e.description(truncate(&description, MAX_DESCRIPTION))
+        .colour(embed_colour(rolled))
+        .footer(|f| f.text(format!("Seed: {:016x}", rolled.seed)));
+
+    if let Some(comment) = &rolled.comment {
+        e.title(truncate(comment, MAX_TITLE));
+    }
+
+    for (name, value) in fields {
+        e.field(name, value, false);
+    }
+
+    e
+}
+
+/// The embed colour for a set of rolls: green for a critical success, red for
+/// a critical failure, and the usual colour otherwise or for a mixture.
+fn embed_colour(rolled: &Rolled) -> u32 {
+    const SUCCESS: u32 = 3_066_993;
+    const FAILURE: u32 = 15_158_332;
+    const NEUTRAL: u32 = 15_385_601;
+
+    let criticals = rolled.rolls
+        .iter()
+        .flat_map(|(_, roll)| roll.rolls())
+        .filter_map(|r| r.critical())
+        .collect::<Vec<_>>();
+
+    let successes = criticals.contains(&Critical::Success);
+    let failures = criticals.contains(&Critical::Failure);
+
+    match (successes, failures) {
+        (true, false) => SUCCESS,
+        (false, true) => FAILURE,
+        _ => NEUTRAL,
+    }
+}
+
+/// Record each roll in the roll history.
+fn record(rolled: &Rolled, channel: ChannelId, user: UserId, message: MessageId) {
+    let timestamp = Utc::now();
+
+    let entries = rolled.rolls
+        .iter()
+        .flat_map(|(expression, roll)| roll.rolls().iter().map(move |r| (expression, r)))
+        .map(|(expression, roll)| {
+            let faces = roll.faces();
+
+            NewRollEntry {
+                channel: channel.into(),
+                roller: user.into(),
+                message: message.into(),
+                expression: expression.clone(),
+                result: roll.to_string(),
+                sides: faces.iter().map(|f| f.sides as i32).collect(),
+                faces: faces.iter().map(|f| f.value as i32).collect(),
+                total: roll.total() as i64,
+                timestamp,
+                critical: roll.critical().map(Into::into),
+                seed: Some(rolled.seed as i64),
+                input: Some(rolled.input.clone()),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    err_log!(RollLog::add(&entries));
+}
+
+/// Luck statistics over a series of rolls.
+#[derive(Default)]
+struct Luck {
+    rolls: usize,
+    /// How often each face came up, by the number of sides on the die.
+    faces: BTreeMap<i32, BTreeMap<i32, u64>>,
+    successes: usize,
+    failures: usize,
+    /// The longest run of consecutive rolls that were luckier than average.
+    streak: usize,
+    /// The luck of every die rolled, each from 0 for its worst face to 1 for its best.
+    luck: f64,
+    dice: usize,
+}
+
+impl Luck {
+    /// Tally a series of rolls, oldest first. In roll-under systems, low faces are lucky.
+    fn new<'a>(entries: impl IntoIterator<Item = &'a RollEntry>, low_is_lucky: bool) -> Self {
+        let mut luck = Luck::default();
+        let mut streak = 0;
+
+        for entry in entries {
+            let mut roll_luck = 0.0;
+            let mut roll_dice = 0;
+
+            for (&sides, &value) in entry.sides.iter().zip(&entry.faces) {
+                if sides < 2 || value < 1 || value > sides {
+                    continue;
+                }
+
+                *luck.faces.entry(sides).or_default().entry(value).or_default() += 1;
+
+                let face = f64::from(value - 1) / f64::from(sides - 1);
+                roll_luck += if low_is_lucky { 1.0 - face } else { face };
+                roll_dice += 1;
+            }
+
+            match entry.critical.map(Critical::from) {
+                Some(Critical::Success) => luck.successes += 1,
+                Some(Critical::Failure) => luck.failures += 1,
+                None => (),
+            }
+
+            if roll_dice > 0 {
+                streak = if roll_luck / roll_dice as f64 > 0.5 { streak + 1 } else { 0 };
+                luck.streak = luck.streak.max(streak);
+            }
+
+            luck.rolls += 1;
+            luck.luck += roll_luck;
+            luck.dice += roll_dice;
+        }
+
+        luck
+    }
+
+    /// How much luckier than expected the dice were, as a percentage.
+    fn score(&self) -> f64 {
+        if self.dice == 0 {
+            0.0
+        } else {
+            (self.luck / self.dice as f64 - 0.5) * 100.0
+        }
+    }
+}
+
+impl fmt::Display for Luck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (&sides, counts) in &self.faces {
+            let n = counts.values().sum::<u64>();
+            let sum = counts.iter().map(|(&v, &c)| f64::from(v) * c as f64).sum::<f64>();
+
+            write!(f, "d{}: {} dice, average {:.2} vs {:.2} expected",
+                   sides, n, sum / n as f64, f64::from(sides + 1) / 2.0)?;
+
+            if sides == 6 || sides == 20 {
+                let observed = (1..=sides).map(|v| counts.get(&v).cloned().unwrap_or(0)).collect::<Vec<_>>();
+
+                if let Some((statistic, p)) = dice::chi_square(&observed) {
+                    write!(f, ", χ² {:.2} (p = {:.3})", statistic, p)?;
+                }
+            }
+
+            writeln!(f)?;
+        }
+
+        writeln!(f, "Overall: {:+.1}% luck over {} dice", self.score(), self.dice)?;
+        writeln!(f, "Critical successes: {}, critical failures: {}", self.successes, self.failures)?;
+        write!(f, "Longest lucky streak: {} roll{}", self.streak, if self.streak == 1 { "" } else { "s" })
+    }
+}
+
+pub fn handle_roll(ctx: &Context, channel: ChannelId, user: UserId, input: &str) {
+    let seed = rand::random::<u64>();
+    let rolled = roll_input(input, channel, game_system(ctx, channel), seed);
+
+    if rolled.rolls.is_empty() {
+        let content = format!("**{}**: {}", user.mention(), rolled.errors());
+        err_log!(channel.say(&ctx, content));
+        return;
+    }
+
+    let fields = embed_fields(&rolled).filter(|_| embed_mode(ctx, channel, user) && can_embed(ctx, channel));
+
+    let plain = || {
+        let content = format!("**{} rolled:**{}\n```{}```{}Seed: `{:016x}`",
+                              user.mention(), rolled.comment(), rolled.results(), rolled.errors(), seed);
+
+        channel.say(&ctx, content)
+    };
+
+    let sent = if let Some(fields) = fields {
+        let heading = format!("{} rolled", user.mention());
+        let embedded = channel.send_message(&ctx, |m| m.embed(|e| build_embed(e, &heading, &rolled, fields)));
+
+        // Fall back to plain text if Discord refuses the embed for any reason.
+        embedded.or_else(|err| {
+            log::warn!("[{}:{}] {:?}", line!(), column!(), err);
+            plain()
+        })
+    } else {
+        plain()
+    };
+
+    match sent {
         Err(err) => log::warn!("[{}:{}] {:?}", line!(), column!(), err),
         Ok(msg) => {
-            let mut data = ctx.data.write();
-            let cache = data.entry::<DiceCache>().or_insert_with(Default::default);
-            cache.insert(msg.id, input.to_string());
+            // React separately, so a failed reaction can't be taken for a failed embed.
+            err_log!(msg.react(&ctx, '🎲'));
+            record(&rolled, channel, user, msg.id);
+            cache_roll(msg.id, input, user, 0);
         }
     }
 }
 
+/// Answer any inline rolls in an ordinary message with a compact summary, in
+/// channels where they're enabled.
+pub fn handle_inline(ctx: &mut Context, msg: &Message) {
+    const MAX_INLINE: usize = 10;
+
+    if msg.author.bot || msg.webhook_id.is_some() || !INLINE.is_match(&msg.content) {
+        return;
+    }
+
+    if !inline_rolls(ctx, msg.channel_id) {
+        return;
+    }
+
+    let bot_id = serenity::utils::with_cache(&ctx, |cache| cache.user.id);
+
+    if crate::framework::is_command(ctx, msg, bot_id) {
+        return;
+    }
+
+    let input = INLINE
+        .captures_iter(&msg.content)
+        .take(MAX_INLINE)
+        .map(|cap| cap[1].replace('#', " "))
+        .join(";");
+
+    let seed = rand::random::<u64>();
+    let rolled = roll_input(&input, msg.channel_id, game_system(ctx, msg.channel_id), seed);
+
+    let results = rolled.rolls
+        .iter()
+        .flat_map(|(expression, roll)| roll.rolls().iter().map(move |r| (expression, r)))
+        .map(|(expression, r)| format!("`{}`: {} → **{}**", expression, r.markdown(), r.summary()))
+        .join("\n");
+
+    let content = format!("**{} rolled:**{}\n{}\n{}Seed: `{:016x}`",
+                          msg.author.mention(), rolled.comment(), results, rolled.errors(), seed);
+
+    match msg.channel_id.say(&ctx, content) {
+        Err(err) => log::warn!("[{}:{}] {:?}", line!(), column!(), err),
+        Ok(sent) => record(&rolled, msg.channel_id, msg.author.id, sent.id),
+    }
+}
+
+/// Reroll a cached roll when someone reacts to it, one reroll of each message at a time.
+pub fn handle_reroll(ctx: &Context, re: &Reaction) {
+    let lock = REROLLING.lock().entry(re.message_id).or_default().clone();
+
+    {
+        let _turn = lock.lock();
+        reroll(ctx, re);
+    }
+
+    // Locks are only handed out while the map is held, so no one else can be
+    // about to take this one if only the map and we still hold it.
+    let mut rerolling = REROLLING.lock();
+
+    if Arc::strong_count(&lock) == 2 {
+        rerolling.remove(&re.message_id);
+    }
+}
+
+/// Reroll a cached roll, adding the new results to the original message
+/// beneath any earlier ones.
+fn reroll(ctx: &Context, re: &Reaction) {
+    const MAX_CONTENT: usize = 2000;
+
+    let cached = match cached_roll(re.message_id) {
+        Some(cached) => cached,
+        None => {
+            log::info!("Die roll is not in message cache.");
+            return;
+        }
+    };
+
+    // Let the reroller react again, but keep our own reaction on the message.
+    err_log!(re.channel_id.delete_reaction(&ctx, re.message_id, Some(re.user_id), '🎲'));
+
+    let roller = UserId(cached.roller as u64);
+
+    if re.user_id != roller && own_rerolls(ctx, re.channel_id) {
+        log::info!("Only {} may reroll this roll.", roller);
+        return;
+    }
+
+    let mut msg = match re.message(&ctx) {
+        Ok(msg) => msg,
+        Err(err) => {
+            log::warn!("[{}:{}] {:?}", line!(), column!(), err);
+            return;
+        }
+    };
+
+    let seed = rand::random::<u64>();
+    let rolled = roll_input(&cached.input, re.channel_id, game_system(ctx, re.channel_id), seed);
+    let rerolls = cached.rerolls + 1;
+
+    // A roll shown as an embed is replaced by an embed of the reroll, if it fits in one.
+    if let Some(fields) = embed_fields(&rolled).filter(|_| !msg.embeds.is_empty()) {
+        let heading = format!("Reroll #{} by {}", rerolls + 1, re.user_id.mention());
+
+        if let Err(err) = msg.edit(&ctx, |m| m.embed(|e| build_embed(e, &heading, &rolled, fields))) {
+            log::warn!("[{}:{}] {:?}", line!(), column!(), err);
+            return;
+        }
+
+        record(&rolled, re.channel_id, re.user_id, msg.id);
+        cache_roll(msg.id, &cached.input, roller, rerolls);
+        return;
+    }
+
+    let section = if rolled.rolls.is_empty() {
+        format!("**Reroll #{} by {}:** {}", rerolls + 1, re.user_id.mention(), rolled.errors())
+    } else {
+        format!("**Reroll #{} by {}:**{}\n```{}```{}Seed: `{:016x}`",
+                rerolls + 1, re.user_id.mention(), rolled.comment(), rolled.results(), rolled.errors(), seed)
+    };
+
+    let content = if msg.content.is_empty() { section.clone() } else { format!("{}\n\n{}", msg.content, section) };
+
+    // Start a new message once the original can't hold any more rerolls.
+    let id = if content.chars().count() <= MAX_CONTENT {
+        match msg.edit(&ctx, |m| m.content(content)) {
+            Ok(()) => msg.id,
+            Err(err) => {
+                log::warn!("[{}:{}] {:?}", line!(), column!(), err);
+                return;
+            }
+        }
+    } else {
+        match re.channel_id.send_message(&ctx, |m| m.content(section).reactions(Some('🎲'))) {
+            Ok(msg) => msg.id,
+            Err(err) => {
+                log::warn!("[{}:{}] {:?}", line!(), column!(), err);
+                return;
+            }
+        }
+    };
+
+    record(&rolled, re.channel_id, re.user_id, id);
+    cache_roll(id, &cached.input, roller, rerolls);
+}
+
+/// Roll in secret, sending the results by DM to the channel's GM and, unless
+/// the roll is blind, to the roller.
+fn handle_secret_roll(ctx: &Context, channel: ChannelId, user: UserId, input: &str, blind: bool) {
+    let gm = match CharTrack::get_channel(channel.into()) {
+        Ok(ch) => Some(UserId(ch.gm as u64)),
+        Err(diesel::result::Error::NotFound) => None,
+        Err(err) => {
+            log::warn!("[{}:{}] {:?}", line!(), column!(), err);
+            None
+        }
+    };
+
+    if blind && gm.is_none() {
+        err_log!(channel.say(&ctx, format!("**{}**: Sorry, there's no GM here to roll blind for.", user.mention())));
+        return;
+    }
+
+    let seed = rand::random::<u64>();
+    let rolled = roll_input(input, channel, game_system(ctx, channel), seed);
+
+    // Errors quote the expression, so they go only to the roller.
+    if rolled.rolls.is_empty() {
+        let content = format!("**Couldn't roll in {}:** {}", channel.mention(), rolled.errors());
+
+        if let Err(err) = user.create_dm_channel(&ctx).and_then(|dm| dm.id.say(&ctx, content)) {
+            log::warn!("[{}:{}] {:?}", line!(), column!(), err);
+            err_log!(channel.say(&ctx, format!("**{}**: Sorry, I couldn't read that, and couldn't DM you why.", user.mention())));
+        }
+
+        return;
+    }
+
+    let content = format!("**{} rolled in {}:**{}\n```{}```{}Seed: `{:016x}`",
+                          user.mention(), channel.mention(), rolled.comment(), rolled.results(), rolled.errors(), seed);
+
+    let mut recipients = gm.into_iter().collect::<Vec<_>>();
+
+    if !blind && !recipients.contains(&user) {
+        recipients.push(user);
+    }
+
+    for recipient in recipients {
+        if let Err(err) = recipient.create_dm_channel(&ctx).and_then(|dm| dm.id.say(&ctx, &content)) {
+            log::warn!("[{}:{}] {:?}", line!(), column!(), err);
+            err_log!(channel.say(&ctx, format!("**{}**: Sorry, I couldn't DM the results to {}.", user.mention(), recipient.mention())));
+        }
+    }
+
+    // Secret rolls are neither cached for rerolling nor recorded in the
+    // channel's roll history, where anyone could read them. The notice leaves
+    // out any attribute notes, which would give away what was rolled.
+    let notice = if blind { "rolled blind" } else { "rolled secretly" };
+    let comment = rolled.comment.as_ref().map(|c| format!(" _{}_", c)).unwrap_or_default();
+    err_log!(channel.say(&ctx, format!("**{} {}.**{}", user.mention(), notice, comment)));
+}
+
 #[command]
-#[description("Calculate an expression in modified dice notation.")]
+#[description("Calculate an expression in modified dice notation. Reroll faces with `r` straight after the dice, as in `4d6r1`; repeat a roll with a spaced `r` or `repeat`, as in `3d6 r3`.")]
 #[usage("[expr][; expr...]`\nFor details, see https://github.com/Celti/eris/wiki/Dice-Expressions `\u{200B}")]
 fn roll(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
     handle_roll(ctx, msg.channel_id, msg.author.id, args.message());
@@ -43,8 +809,496 @@ fn roll(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
     Ok(())
 }
 
+#[command]
+#[description("Roll in secret, sending the results by DM to you and the channel's GM. Secret rolls aren't recorded, so they're left out of `rolls` and `luck`.")]
+#[usage("[expr][; expr...]")]
+fn groll(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    handle_secret_roll(ctx, msg.channel_id, msg.author.id, args.message(), false);
+
+    Ok(())
+}
+
+#[command]
+#[description("Roll blind, sending the results by DM to the channel's GM only. Blind rolls aren't recorded, so they're left out of `rolls` and `luck`.")]
+#[usage("[expr][; expr...]")]
+fn broll(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    handle_secret_roll(ctx, msg.channel_id, msg.author.id, args.message(), true);
+
+    Ok(())
+}
+
+#[command]
+#[description("Resolve an opposed roll between two participants, such as a GURPS Quick Contest.")]
+#[usage("<name> <expr> [vs target] ; <name> <expr> [vs target]")]
+fn contest(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    let mut input = args.message().splitn(2, '#');
+    let sides = input.next().unwrap_or_default().split(';').map(str::trim).collect::<Vec<_>>();
+
+    let participants = match sides.as_slice() {
+        [first, second] => [first, second]
+            .iter()
+            .map(|s| s.splitn(2, char::is_whitespace).collect_tuple::<(&str, &str)>())
+            .collect::<Option<Vec<_>>>(),
+        _ => None,
+    };
+
+    let participants = match participants {
+        Some(participants) => participants,
+        None => {
+            reply!(ctx, msg, "Sorry, I need two participants, each with a roll: `<name> <expr> ; <name> <expr>`");
+            return Ok(());
+        }
+    };
+
+    let system = game_system(ctx, msg.channel_id);
+    let seed = rand::random::<u64>();
+    let mut exprs = participants.iter().map(|(_, expr)| expr.trim()).join(";");
+
+    if let Some(comment) = input.next() {
+        exprs = format!("{}#{}", exprs, comment);
+    }
+
+    let rolled = roll_input(&exprs, msg.channel_id, system, seed);
+
+    let rolls = rolled.rolls.iter().flat_map(|(_, roll)| roll.rolls()).collect::<Vec<_>>();
+
+    let (first, second) = match (rolled.errors.is_empty(), rolls.as_slice()) {
+        (true, [first, second]) => (*first, *second),
+        (true, _) => {
+            reply!(ctx, msg, "Sorry, each participant needs exactly one roll.");
+            return Ok(());
+        }
+        (false, _) => {
+            reply!(ctx, msg, "{}", rolled.errors());
+            return Ok(());
+        }
+    };
+
+    let (a, b) = (participants[0].0, participants[1].0);
+
+    let result = match dice::contest(first, second) {
+        Some(Contest::First(0)) => format!("{} wins the tie.", a),
+        Some(Contest::Second(0)) => format!("{} wins the tie.", b),
+        Some(Contest::First(by)) => format!("{} wins by {}.", a, by),
+        Some(Contest::Second(by)) => format!("{} wins by {}.", b, by),
+        Some(Contest::Tie) => String::from("It's a tie."),
+        None => {
+            reply!(ctx, msg, "Sorry, in {} each roll needs a target, like `3d6 vs 12`.", system);
+            return Ok(());
+        }
+    };
+
+    let title = if system == GameSystem::Gurps { "Quick Contest" } else { "Contest" };
+
+    let content = format!("**{}:**{}\n{}: {} → **{}**\n{}: {} → **{}**\n**{}** Seed: `{:016x}`",
+                          title, rolled.comment(),
+                          a, first.markdown(), first.summary(),
+                          b, second.markdown(), second.summary(),
+                          result, seed);
+
+    match msg.channel_id.say(&ctx, content) {
+        Err(err) => log::warn!("[{}:{}] {:?}", line!(), column!(), err),
+        Ok(sent) => record(&rolled, msg.channel_id, msg.author.id, sent.id),
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Calculate the exact odds of an expression in modified dice notation, and its chance of success against any target.")]
+#[usage("<expr> [vs target]")]
+fn odds(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    let input = args.message().splitn(2, '#').next().unwrap_or_default().trim();
+
+    let input = match substitute(input, msg.channel_id, &mut Vec::new()) {
+        Ok(input) => input,
+        Err(err) => {
+            reply!(ctx, msg, "Sorry, I couldn't read that:\n```{}```", err);
+            return Ok(());
+        }
+    };
+
+    let expr = match input.parse::<DiceExpr>() {
+        Ok(expr) => expr,
+        Err(err) => {
+            reply!(ctx, msg, "Sorry, I couldn't read that:\n```{}```", err.snippet(&input));
+            return Ok(());
+        }
+    };
+
+    match expr.odds(game_system(ctx, msg.channel_id), &LIMITS) {
+        Ok(odds) => say!(ctx, msg, "**Odds for {}:**\n```{}```", expr, odds),
+        Err(err) => reply!(ctx, msg, "Sorry, I can't work out those odds: {}.", err),
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Page through the recent rolls in this channel, optionally by a single user or matching an expression. Secret and blind rolls aren't included.")]
+#[usage("[@user] [page] [expression]")]
+fn rolls(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    const PER_PAGE: i64 = 10;
+
+    let mut roller = None;
+    let mut page = 1;
+    let mut partial = Vec::new();
+
+    for arg in args.message().split_whitespace() {
+        if let Ok(user) = arg.parse::<UserId>() {
+            roller = Some(user);
+        } else if let Ok(p) = arg.parse::<i64>() {
+            page = p.max(1);
+        } else {
+            partial.push(arg);
+        }
+    }
+
+    let partial = partial.join(" ");
+    let partial = if partial.is_empty() { None } else { Some(partial.as_str()) };
+
+    if (page - 1).checked_mul(PER_PAGE).is_none() {
+        say!(ctx, msg, "Sorry, there's no page {}.", page);
+        return Ok(());
+    }
+
+    let entries = RollLog::find(msg.channel_id.into(), roller.map(i64::from), partial, page - 1, PER_PAGE)?;
+
+    if entries.is_empty() {
+        say!(ctx, msg, "Sorry, I don't have any matching rolls on page {}.", page);
+        return Ok(());
+    }
+
+    let mut names = HashMap::new();
+
+    let list = entries
+        .iter()
+        .map(|e| {
+            let name = names.entry(e.roller).or_insert_with(|| {
+                UserId(e.roller as u64).to_user(&ctx).map(|u| u.name).unwrap_or_else(|_| e.roller.to_string())
+            });
+
+            format!("{} {}: {}", e.timestamp.format("%Y-%m-%d %H:%M"), name, e.result)
+        })
+        .join("\n");
+
+    say!(ctx, msg, "**Recent rolls (page {}):**\n```{}```", page, list);
+
+    Ok(())
+}
+
+#[command]
+#[description("Show how lucky someone's recent dice have been in this channel, or the channel's luckiest rollers. Secret and blind rolls aren't counted.")]
+#[usage("[@user|channel]")]
+fn luck(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    const LEADERS: usize = 10;
+    const MIN_DICE: usize = 20;
+    /// The most recent rolls counted, so that busy channels stay quick to tally.
+    const HISTORY: i64 = 5000;
+
+    let low_is_lucky = match game_system(ctx, msg.channel_id) {
+        GameSystem::Gurps | GameSystem::D100 => true,
+        _ => false,
+    };
+
+    let arg = args.message().trim();
+
+    if arg == "channel" {
+        let entries = RollLog::history(msg.channel_id.into(), None, HISTORY)?;
+        let mut by_roller = HashMap::<i64, Vec<&RollEntry>>::new();
+
+        for entry in &entries {
+            by_roller.entry(entry.roller).or_default().push(entry);
+        }
+
+        let mut board = by_roller
+            .into_iter()
+            .map(|(roller, entries)| (roller, Luck::new(entries, low_is_lucky)))
+            .filter(|(_, luck)| luck.dice >= MIN_DICE)
+            .collect::<Vec<_>>();
+
+        if board.is_empty() {
+            say!(ctx, msg, "Sorry, nobody here has rolled enough dice for a leaderboard yet.");
+            return Ok(());
+        }
+
+        board.sort_by(|(_, a), (_, b)| b.score().partial_cmp(&a.score()).unwrap_or(Ordering::Equal));
+
+        let list = board
+            .iter()
+            .take(LEADERS)
+            .enumerate()
+            .map(|(i, (roller, luck))| {
+                let name = UserId(*roller as u64).to_user(&ctx).map(|u| u.name).unwrap_or_else(|_| roller.to_string());
+                format!("{:>2}. {}: {:+.1}% over {} dice, {} critical successes, {} critical failures",
+                        i + 1, name, luck.score(), luck.dice, luck.successes, luck.failures)
+            })
+            .join("\n");
+
+        say!(ctx, msg, "**Luckiest rollers in this channel:**\n```{}```", list);
+        return Ok(());
+    }
+
+    let user = if arg.is_empty() {
+        msg.author.id
+    } else {
+        match arg.parse::<UserId>() {
+            Ok(user) => user,
+            Err(_) => {
+                reply!(ctx, msg, "Sorry, I don't know who `{}` is.", arg);
+                return Ok(());
+            }
+        }
+    };
+
+    let entries = RollLog::history(msg.channel_id.into(), Some(user.into()), HISTORY)?;
+
+    if entries.is_empty() {
+        say!(ctx, msg, "Sorry, {} hasn't rolled anything here yet.", user.mention());
+        return Ok(());
+    }
+
+    let luck = Luck::new(&entries, low_is_lucky);
+    say!(ctx, msg, "**Luck for {} in this channel ({} rolls):**\n```{}```", user.mention(), luck.rolls, luck);
+
+    Ok(())
+}
+
+#[command]
+#[description("Reproduce a roll exactly from the seed shown beneath it, replaying the expressions recorded with it here unless others are given.")]
+#[usage("<seed> [expr][; expr...]")]
+#[min_args(1)]
+fn verify(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let seed = u64::from_str_radix(args.single::<String>()?.trim_matches('`'), 16)?;
+
+    let input = if args.rest().trim().is_empty() {
+        match RollLog::by_seed(msg.channel_id.into(), seed as i64).optional()?.and_then(|e| e.input) {
+            Some(input) => input,
+            None => {
+                reply!(ctx, msg, "Sorry, I don't have a roll from seed `{:016x}` here. Give its expressions after the seed to reproduce it.", seed);
+                return Ok(());
+            }
+        }
+    } else {
+        args.rest().to_string()
+    };
+
+    let rolled = roll_input(&input, msg.channel_id, game_system(ctx, msg.channel_id), seed);
+
+    if rolled.rolls.is_empty() {
+        reply!(ctx, msg, "{}", rolled.errors());
+    } else {
+        reply!(ctx, msg, "Seed `{:016x}` rolls:{}\n```{}```{}", seed, rolled.comment(), rolled.results(), rolled.errors());
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Show or change whether your rolls, or everyone's in the current guild, are shown as embeds.")]
+#[usage("[guild] [on|off|default]")]
+fn embeds(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    let guild = args.message().starts_with("guild");
+    let value = args.message().trim_start_matches("guild").trim();
+
+    if value.is_empty() {
+        let mode = if embed_mode(ctx, msg.channel_id, msg.author.id) { "embeds" } else { "text" };
+        reply!(ctx, msg, "Your rolls here are shown as {}.", mode);
+        return Ok(());
+    }
+
+    let embed = match value {
+        "on" => Some(true),
+        "off" => Some(false),
+        "default" => None,
+        _ => {
+            reply!(ctx, msg, "Sorry, I don't understand `{}`. Try on, off or default.", value);
+            return Ok(());
+        }
+    };
+
+    let id: i64 = if guild {
+        let allowed = msg.guild(&ctx).map_or(false, |g| g.read().member_permissions(msg.author.id).manage_guild());
+
+        match msg.guild_id {
+            Some(guild_id) if allowed => guild_id.into(),
+            Some(_) => {
+                reply!(ctx, msg, "Sorry, you need permission to manage this guild to change its setting.");
+                return Ok(());
+            }
+            None => {
+                reply!(ctx, msg, "Sorry, there's no guild here.");
+                return Ok(());
+            }
+        }
+    } else {
+        msg.author.id.into()
+    };
+
+    let mut data = ctx.data.write();
+    let cache = data
+        .entry::<DiceSettingsCache>()
+        .or_insert_with(|| DB::get().unwrap());
+
+    let mut settings = cache.get(&id).cloned().unwrap_or(DiceSettings { id, ..Default::default() });
+    settings.embed = embed;
+
+    cache.insert(id, DB::set(&settings)?);
+
+    let whose = if guild { "This guild's" } else { "Your" };
+
+    match embed {
+        Some(true) => reply!(ctx, msg, "{} rolls will be shown as embeds.", whose),
+        Some(false) => reply!(ctx, msg, "{} rolls will be shown as text.", whose),
+        None => reply!(ctx, msg, "{} rolls will be shown the default way.", whose),
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Show or change who may reroll dice in the current channel: anyone, or only the original roller.")]
+#[usage("[anyone|roller]")]
+fn rerolls(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    let value = args.message().trim();
+
+    if value.is_empty() {
+        let who = if own_rerolls(ctx, msg.channel_id) { "only the original roller" } else { "anyone" };
+        reply!(ctx, msg, "Rolls here may be rerolled by {}.", who);
+        return Ok(());
+    }
+
+    let own = match value {
+        "anyone" => None,
+        "roller" => Some(true),
+        _ => {
+            reply!(ctx, msg, "Sorry, I don't understand `{}`. Try anyone or roller.", value);
+            return Ok(());
+        }
+    };
+
+    let gm = CharTrack::get_channel(msg.channel_id.into()).optional()?.map(|ch| UserId(ch.gm as u64));
+    let manager = msg.guild(&ctx).map_or(true, |g| g.read().permissions_in(msg.channel_id, msg.author.id).manage_channels());
+
+    if gm != Some(msg.author.id) && !manager {
+        reply!(ctx, msg, "Sorry, only the GM can change who may reroll here.");
+        return Ok(());
+    }
+
+    let id = { let i: i64 = msg.channel_id.into(); -i };
+
+    let mut data = ctx.data.write();
+    let cache = data
+        .entry::<DiceSettingsCache>()
+        .or_insert_with(|| DB::get().unwrap());
+
+    let mut settings = cache.get(&id).cloned().unwrap_or(DiceSettings { id, ..Default::default() });
+    settings.own_rerolls = own;
+
+    cache.insert(id, DB::set(&settings)?);
+
+    if own.is_some() {
+        reply!(ctx, msg, "Only the original roller may now reroll dice here.");
+    } else {
+        reply!(ctx, msg, "Anyone may now reroll dice here.");
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Show or change whether inline rolls like `[[3d6+2]]` in ordinary messages are answered in the current channel.")]
+#[usage("[on|off]")]
+#[required_permissions(MANAGE_CHANNELS)]
+fn inline(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    let value = args.message().trim();
+
+    let inline = match value {
+        "" => {
+            let state = if inline_rolls(ctx, msg.channel_id) { "on" } else { "off" };
+            reply!(ctx, msg, "Inline rolls are {} here.", state);
+            return Ok(());
+        }
+        "on" => Some(true),
+        "off" => None,
+        _ => {
+            reply!(ctx, msg, "Sorry, I don't understand `{}`. Try on or off.", value);
+            return Ok(());
+        }
+    };
+
+    let id = { let i: i64 = msg.channel_id.into(); -i };
+
+    let mut data = ctx.data.write();
+    let cache = data
+        .entry::<DiceSettingsCache>()
+        .or_insert_with(|| DB::get().unwrap());
+
+    let mut settings = cache.get(&id).cloned().unwrap_or(DiceSettings { id, ..Default::default() });
+    settings.inline = inline;
+
+    cache.insert(id, DB::set(&settings)?);
+
+    if inline.is_some() {
+        reply!(ctx, msg, "Inline rolls are now on here.");
+    } else {
+        reply!(ctx, msg, "Inline rolls are now off here.");
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Show or change the game system used to resolve rolls against a target in the current guild or channel.")]
+#[usage("[channel] [gurps|d20|d100|pbta|fate|default]")]
+#[required_permissions(MANAGE_CHANNELS)]
+fn system(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    let name = args.message().trim_start_matches("channel").trim();
+
+    if name.is_empty() {
+        reply!(ctx, msg, "The current game system is {}.", game_system(ctx, msg.channel_id));
+        return Ok(());
+    }
+
+    let system = if name == "default" {
+        None
+    } else {
+        match name.parse::<GameSystem>() {
+            Ok(system) => Some(system),
+            Err(err) => {
+                reply!(ctx, msg, "Sorry, {}. Try gurps, d20, d100, pbta or fate.", err);
+                return Ok(());
+            }
+        }
+    };
+
+    let id = if msg.guild_id.is_none() || args.message().starts_with("channel") {
+        let i: i64 = msg.channel_id.into(); -i
+    } else {
+        msg.guild_id.unwrap().into()
+    };
+
+    let mut data = ctx.data.write();
+    let cache = data
+        .entry::<DiceSettingsCache>()
+        .or_insert_with(|| DB::get().unwrap());
+
+    let mut settings = cache.get(&id).cloned().unwrap_or(DiceSettings { id, ..Default::default() });
+    settings.system = system.map(Into::into);
+
+    cache.insert(id, DB::set(&settings)?);
+
+    if let Some(system) = system {
+        reply!(ctx, msg, "Changed game system to {}.", system);
+    } else {
+        reply!(ctx, msg, "Changed game system to default.");
+    }
+
+    Ok(())
+}
+
 group!({
     name: "dice",
     options: {},
-    commands: [roll]
+    commands: [roll, groll, broll, contest, odds, rolls, luck, embeds, rerolls, inline, system, verify]
 });
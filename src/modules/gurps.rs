@@ -1,9 +1,213 @@
-use serenity::framework::standard::CommandError;
-use std::process::Command;
+use crate::db::CharTrack;
+use crate::ext::dice::{self, DiceExpr, Roll};
+use crate::ext::gurps::{self, basic_lift, Attack, DamageType, Encumbrance, Location};
+use crate::ext::tables::{self, TABLES};
+use crate::ext::units::{self, UnitError};
+use crate::modules::{chartrack, dice::LIMITS};
+use lazy_static::lazy_static;
+use regex::Regex;
 use serenity::client::Context;
 use serenity::framework::standard::{Args, CommandResult};
 use serenity::framework::standard::macros::{command, group};
 use serenity::model::channel::Message;
+use serenity::model::misc::Mentionable;
+
+lazy_static! {
+    /// An armour divisor, alone or after the dice, like `3d(2)` or `(0.5)`.
+    static ref DIVISOR: Regex = Regex::new(r"^(.*)\((\d*\.?\d+)\)$").unwrap();
+}
+
+#[command]
+#[aliases(dmg)]
+#[description("Roll damage and work out the injury it causes, optionally applying it to a tracked character's HP (or FP for fatigue damage).")]
+#[usage(r#"<dice>[(divisor)] [type] [dr <DR>] [location] [to "<Name>"]"#)]
+#[min_args(1)]
+fn damage(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let mut terms = Vec::new();
+    let mut attack = Attack::default();
+    let mut dr = 0;
+    let mut target = None;
+
+    while !args.is_empty() {
+        let token = args.quoted().single::<String>()?;
+        let lower = token.to_lowercase();
+
+        if lower == "dr" {
+            dr = args.single::<isize>()?;
+        } else if lower == "to" || lower == "on" {
+            target = Some(args.quoted().single::<String>()?);
+        } else if lower.starts_with("dr") && lower[2..].parse::<isize>().is_ok() {
+            dr = lower[2..].parse()?;
+        } else if let Ok(kind) = token.parse::<DamageType>() {
+            attack.kind = kind;
+        } else if let Ok(location) = token.parse::<Location>() {
+            attack.location = location;
+        } else if let Some(cap) = DIVISOR.captures(&token) {
+            attack.divisor = cap[2].parse()?;
+
+            if !cap[1].is_empty() {
+                terms.push(cap[1].to_string());
+            }
+        } else {
+            terms.push(token);
+        }
+    }
+
+    if terms.is_empty() {
+        reply!(ctx, msg, "Sorry, I need a damage roll, like `2d+1 cut`.");
+        return Ok(());
+    }
+
+    if attack.divisor <= 0.0 {
+        reply!(ctx, msg, "Sorry, an armour divisor must be more than 0.");
+        return Ok(());
+    }
+
+    let input = terms.join(" ");
+
+    let (roll, seed) = match roll_one(ctx, msg, &input) {
+        Some(rolled) => rolled,
+        None => return Ok(()),
+    };
+
+    // Fatigue damage comes off FP, and can't cripple.
+    let pool = if attack.kind == DamageType::Fatigue { "FP" } else { "HP" };
+
+    let hp = target.as_ref().filter(|_| pool == "HP").and_then(|who| {
+        let ch = CharTrack::get_character_by_pair(who, msg.channel_id.into()).ok()?;
+        let at = CharTrack::get_attribute(pool, ch.pin).ok()?;
+        let hp = if at.maximum > 0 { at.maximum } else { at.value };
+        Some(hp as isize)
+    });
+
+    let injury = attack.wound(roll.total(), dr, hp);
+
+    #[allow(clippy::float_cmp)] // Divisors are given exactly.
+    let divisor = if attack.divisor == 1.0 { String::new() } else { format!(" ({})", attack.divisor) };
+
+    let armour = if injury.dr == dr { format!("DR {}", dr) } else { format!("DR {} → {}", dr, injury.dr) };
+
+    let crippled = if injury.crippled { format!(" The {} is crippled!", attack.location) } else { String::new() };
+
+    say!(ctx, msg, "**{} rolled damage:** {} → **{}**\n**{} {}{}** to the {} vs {}: {} penetrating × {} = **{} injury**.{} Seed: `{:016x}`",
+         msg.author.mention(), roll.markdown(), roll.summary(),
+         injury.basic, attack.kind, divisor, attack.location, armour,
+         injury.penetrating, injury.multiplier, injury.injury, crippled, seed);
+
+    if let Some(who) = target.filter(|_| injury.injury > 0) {
+        let comment = format!("Took {} {} injury to the {}.", injury.injury, attack.kind, attack.location);
+        chartrack::adjust(ctx, msg, &who, pool, -(injury.injury as i32), &comment)?;
+    }
+
+    Ok(())
+}
+
+#[command]
+#[aliases(lookup)]
+#[description("Roll on one of the GURPS tables, with an optional modifier.")]
+#[usage("<table> [modifier]")]
+#[min_args(1)]
+fn table(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let name = args.single::<String>()?;
+    roll_table(ctx, msg, &name, args.rest())
+}
+
+#[command]
+#[aliases(react)]
+#[description("Roll on the Reaction Table, with any reaction modifiers.")]
+#[usage("[modifier]")]
+fn reaction(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    roll_table(ctx, msg, "reaction", args.rest())
+}
+
+#[command]
+#[description("Roll on the Fright Check Table, adding the margin of failure.")]
+#[usage("[margin of failure]")]
+fn fright(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    roll_table(ctx, msg, "fright", args.rest())
+}
+
+#[command]
+#[description("Roll on the Critical Hit Table.")]
+#[num_args(0)]
+fn crithit(ctx: &mut Context, msg: &Message) -> CommandResult {
+    roll_table(ctx, msg, "crithit", "")
+}
+
+#[command]
+#[aliases(fumble)]
+#[description("Roll on the Critical Miss Table.")]
+#[num_args(0)]
+fn critmiss(ctx: &mut Context, msg: &Message) -> CommandResult {
+    roll_table(ctx, msg, "critmiss", "")
+}
+
+#[command]
+#[description("Roll on the Critical Head Blow Table.")]
+#[num_args(0)]
+fn headblow(ctx: &mut Context, msg: &Message) -> CommandResult {
+    roll_table(ctx, msg, "headblow", "")
+}
+
+#[command]
+#[aliases(location)]
+#[description("Roll a random hit location.")]
+#[num_args(0)]
+fn hitloc(ctx: &mut Context, msg: &Message) -> CommandResult {
+    roll_table(ctx, msg, "hitloc", "")
+}
+
+#[command]
+#[aliases(enc)]
+#[description("Work out encumbrance, Move and Dodge for a load, and what can be lifted, from ST, Basic Lift (with a unit, like `25lb`) or a tracked character's ST.")]
+#[usage(r#"<ST|BL|"Name"> <Basic Move> <Dodge> <load>"#)]
+#[min_args(4)]
+fn encumbrance(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let strength = args.quoted().single::<String>()?;
+    let basic_move = args.single::<isize>()?;
+    let dodge = args.single::<isize>()?;
+
+    let (source, lift) = match strength.parse::<f64>() {
+        Ok(st) => (format!("ST {}", st), basic_lift(st)),
+        Err(_) => match units::parse(&strength).and_then(|q| pounds(&q)) {
+            Ok(lift) => (String::from("BL"), lift),
+            Err(_) => match tracked_st(&strength, msg) {
+                Ok(st) => (format!("{}'s ST {}", strength, st), basic_lift(st)),
+                Err(err) => {
+                    reply!(ctx, msg, "Sorry, {}.", err);
+                    return Ok(());
+                }
+            },
+        },
+    };
+
+    // A bare number is taken to be pounds.
+    let load = units::parse(args.rest()).and_then(|q| if q.is_dimensionless() { Ok(q.value) } else { pounds(&q) });
+
+    let load = match load {
+        Ok(load) => load,
+        Err(err) => {
+            reply!(ctx, msg, "Sorry, {}.", err);
+            return Ok(());
+        }
+    };
+
+    let status = match Encumbrance::for_load(lift, load) {
+        Some(enc) => format!("**{} encumbrance** (level {}): Move {} → {}, Dodge {} → {}.",
+                             enc, enc.level(), basic_move, enc.reduce_move(basic_move), dodge, dodge - enc.level()),
+        None => String::from("**Overloaded:** more than 10 × BL can't be moved at all."),
+    };
+
+    let limits = gurps::LIFTING
+        .iter()
+        .map(|(name, multiple)| format!("**{}** {} lb", name, units::number(lift * multiple)))
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    reply!(ctx, msg, "{} (BL {} lb) carrying {} lb: {}\n{}.", source, units::number(lift), units::number(load), status, limits);
+
+    Ok(())
+}
 
 #[command]
 #[aliases(super)]
@@ -33,7 +237,10 @@ fn linear(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
 #[description("Calculate the speed/range penalty for a given linear measurement.")]
 #[min_args(1)]
 fn range(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
-    reply!(ctx, msg, "{}", -sm(yards(args.message())?));
+    match yards(args.message()) {
+        Ok(yards) => reply!(ctx, msg, "{}", -sm(yards)),
+        Err(err) => reply!(ctx, msg, "Sorry, {}.", err),
+    }
 
     Ok(())
 }
@@ -43,7 +250,10 @@ fn range(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
 #[description("Calculate the size modifier for a given linear measurement.")]
 #[min_args(1)]
 fn size(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
-    reply!(ctx, msg, "{}", sm(yards(args.message())?));
+    match yards(args.message()) {
+        Ok(yards) => reply!(ctx, msg, "{}", sm(yards)),
+        Err(err) => reply!(ctx, msg, "Sorry, {}.", err),
+    }
 
     Ok(())
 }
@@ -54,11 +264,7 @@ fn size(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
 fn st(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
     let st = args.single::<f64>()?;
 
-    let lift = {
-        let lift = 10f64.powf(st / 10.0) * 2.0;
-        let ord  = 10f64.powf((lift.log10() - 1.0).floor());
-        (lift / ord).round() * ord
-    };
+    let lift = basic_lift(st);
 
     let swing = {
         let swing = (st - 6.0) / 4.0;
@@ -100,7 +306,7 @@ fn st(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
 group!({
     name: "GURPS",
     options: {},
-    commands: [linear, range, size, st]
+    commands: [crithit, critmiss, damage, encumbrance, fright, headblow, hitloc, linear, range, reaction, size, st, table]
 });
 
 fn sm(yards: f64) -> f64 {
@@ -120,20 +326,84 @@ fn sm(yards: f64) -> f64 {
     }
 }
 
-fn yards(dist: &str) -> Result<f64, CommandError> {
-    let output = Command::new("/usr/bin/units")
-        .arg("--terse")
-        .arg("--")
-        .arg(dist)
-        .arg("yards")
-        .env("UNITS_ENGLISH", "US")
-        .output()?;
+/// Roll on a table by name, adding any modifier to the dice.
+fn roll_table(ctx: &mut Context, msg: &Message, name: &str, modifier: &str) -> CommandResult {
+    let table = match tables::table(name) {
+        Some(table) => table,
+        None => {
+            let names = TABLES.iter().map(|t| t.name).collect::<Vec<_>>().join(", ");
+            reply!(ctx, msg, "Sorry, I don't know a table called `{}`. Try one of: {}.", name, names);
+            return Ok(());
+        }
+    };
 
-    let yards: f64 = if output.status.success() {
-        String::from_utf8(output.stdout)?.trim().parse()?
-    } else {
-        dist.trim().parse()?
+    let modifier = modifier.trim();
+
+    let input = match modifier.chars().next() {
+        None => table.dice.to_string(),
+        Some('+') | Some('-') => format!("{}{}", table.dice, modifier),
+        Some(_) => format!("{}+{}", table.dice, modifier),
+    };
+
+    let (roll, seed) = match roll_one(ctx, msg, &input) {
+        Some(rolled) => rolled,
+        None => return Ok(()),
+    };
+
+    let (label, text) = table.lookup(roll.total());
+
+    say!(ctx, msg, "**{} rolled on the {}:** {} → **{}**\n**{}:** {} Seed: `{:016x}`",
+         msg.author.mention(), table.title, roll.markdown(), roll.summary(), label, text, seed);
+
+    Ok(())
+}
+
+/// Roll a single expression, replying with any error.
+fn roll_one(ctx: &Context, msg: &Message, input: &str) -> Option<(Roll, u64)> {
+    let expr = match input.parse::<DiceExpr>() {
+        Ok(expr) => expr,
+        Err(err) => {
+            reply!(ctx, msg, "Sorry, I couldn't read that:\n```{}```", err.snippet(input));
+            return None;
+        }
     };
 
-    Ok(yards)
+    let seed = rand::random::<u64>();
+
+    match expr.roll(&mut dice::seeded(seed), &LIMITS) {
+        Ok(roll) => match roll.rolls() {
+            [roll] => Some((roll.clone(), seed)),
+            _ => {
+                reply!(ctx, msg, "Sorry, I need exactly one roll.");
+                None
+            }
+        },
+        Err(err) => {
+            reply!(ctx, msg, "Sorry, {}.", err);
+            None
+        }
+    }
+}
+
+/// A tracked character's ST.
+fn tracked_st(who: &str, msg: &Message) -> Result<f64, String> {
+    let ch = CharTrack::get_character_by_pair(who, msg.channel_id.into()).map_err(|_| format!("I'm not tracking {}", who))?;
+    let at = CharTrack::get_attribute("ST", ch.pin).map_err(|_| format!("{} has no ST", who))?;
+    Ok(f64::from(at.value))
+}
+
+/// A weight in pounds.
+fn pounds(weight: &units::Quantity) -> Result<f64, UnitError> {
+    weight.in_units(&units::parse("lb")?)
+}
+
+/// A distance in yards, taking a bare number to be yards already.
+fn yards(dist: &str) -> Result<f64, UnitError> {
+    let dist = units::parse(dist)?;
+
+    if dist.is_dimensionless() {
+        Ok(dist.value)
+    } else {
+        dist.in_units(&units::parse("yd")?)
+    }
 }
@@ -0,0 +1,197 @@
+use crate::db::Macros as DB;
+use crate::db::model::Macro;
+use crate::modules::dice::{check_input, handle_roll};
+use diesel::result::Error::NotFound;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serenity::client::Context;
+use serenity::framework::standard::macros::{command, group};
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::model::channel::Message;
+
+/// Substitute arguments for the `$1` or `${1:default}` parameters in a macro's body,
+/// looking up each argument by its index from zero.
+fn expand<'a>(body: &str, arg: impl Fn(usize) -> Option<&'a str>) -> Result<String, String> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"\$(?:(\d+)|\{(\d+)(?::([^}]*))?\})").unwrap();
+    }
+
+    let mut missing = None;
+
+    let expanded = RE.replace_all(body, |cap: &Captures<'_>| {
+        let i = cap.get(1).or_else(|| cap.get(2)).map_or("", |m| m.as_str());
+        let arg = i.parse::<usize>().ok().and_then(|i| i.checked_sub(1)).and_then(&arg);
+
+        match (arg, cap.get(3)) {
+            (Some(arg), _) => arg.to_string(),
+            (None, Some(default)) => default.as_str().to_string(),
+            (None, None) => {
+                if missing.is_none() {
+                    missing = Some(format!("${}", i));
+                }
+
+                String::new()
+            }
+        }
+    });
+
+    match missing {
+        Some(param) => Err(param),
+        None => Ok(expanded.into_owned()),
+    }
+}
+
+/// Expand and roll a macro on behalf of the message's author.
+fn run_macro(ctx: &Context, msg: &Message, m: &Macro, args: &str) {
+    let args = args.split_whitespace().collect::<Vec<_>>();
+
+    match expand(&m.body, |i| args.get(i).cloned()) {
+        Ok(input) => handle_roll(ctx, msg.channel_id, msg.author.id, &input),
+        Err(param) => say!(ctx, msg, "Sorry, `{}` needs a value for `{}`: `{}`", m.name, param, m.body),
+    }
+}
+
+/// Run a macro called as a bareword with the given arguments, if the author has
+/// one by that name.
+pub fn handle_bareword(ctx: &Context, msg: &Message, name: &str, args: &str) {
+    let name = name.to_lowercase();
+
+    match DB::find(&name, msg.author.id.into(), msg.channel_id.into()) {
+        Err(NotFound) => (),
+        Err(err) => log::warn!("[{}:{}] {:?}", line!(), column!(), err),
+        Ok(m) => run_macro(ctx, msg, &m, args),
+    }
+}
+
+#[command]
+#[description("Save a roll macro, optionally sharing it with the current channel.")]
+#[usage("[channel] <name> <expr>[; expr...] [# comment]`\nUse `$1`, `$2`… for arguments, and `${1:14}` to give one a default. `\u{200B}")]
+#[min_args(2)]
+fn save(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let shared = args.current() == Some("channel");
+
+    if shared {
+        args.advance();
+    }
+
+    let name = args.single::<String>()?.to_lowercase();
+    let body = args.rest().trim().to_string();
+
+    if body.is_empty() {
+        reply!(ctx, msg, "Sorry, I need something to roll: `[channel] <name> <expr>`");
+        return Ok(());
+    }
+
+    // Check that the macro parses, with a placeholder for every argument.
+    let placeholder = match expand(&body, |_| Some("1")) {
+        Ok(placeholder) => placeholder,
+        Err(param) => {
+            reply!(ctx, msg, "Sorry, `{}` can't be given a value: arguments are numbered from `$1`.", param);
+            return Ok(());
+        }
+    };
+
+    if let Err(err) = check_input(&placeholder) {
+        reply!(ctx, msg, "Sorry, I couldn't read that:\n```{}```", err);
+        return Ok(());
+    }
+
+    let m = Macro {
+        name,
+        owner: msg.author.id.into(),
+        channel: msg.channel_id.into(),
+        shared,
+        body,
+    };
+
+    DB::set(&m)?;
+
+    if shared {
+        say!(ctx, msg, "Saved `{}` and shared it with this channel.", m.name);
+    } else {
+        say!(ctx, msg, "Saved `{}`.", m.name);
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("List your roll macros, and those shared with the current channel.")]
+#[num_args(0)]
+fn list(ctx: &mut Context, msg: &Message) -> CommandResult {
+    /// Leaves room within Discord's 2000 characters for the code block and a note of any left out.
+    const MAX_LIST: usize = 1900;
+
+    let author: i64 = msg.author.id.into();
+    let macros = DB::list(author, msg.channel_id.into())?;
+
+    if macros.is_empty() {
+        say!(ctx, msg, "Sorry, I don't know any macros for you here.");
+        return Ok(());
+    }
+
+    let mut list = String::new();
+    let mut shown = 0;
+
+    for m in &macros {
+        let shared = if m.shared { " (shared)" } else { "" };
+        let line = format!("{}{}: {}\n", m.name, shared, m.body);
+
+        if list.chars().count() + line.chars().count() > MAX_LIST {
+            break;
+        }
+
+        list.push_str(&line);
+        shown += 1;
+    }
+
+    if shown < macros.len() {
+        list.push_str(&format!("…and {} more", macros.len() - shown));
+    }
+
+    say!(ctx, msg, "```{}```", list.trim_end());
+
+    Ok(())
+}
+
+#[command]
+#[aliases(del)]
+#[description("Delete one of your roll macros.")]
+#[usage("<name>")]
+#[num_args(1)]
+fn delete(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let name = args.single::<String>()?.to_lowercase();
+
+    match DB::get(&name, msg.author.id.into()) {
+        Err(NotFound) => say!(ctx, msg, "Sorry, you don't have a macro called `{}`.", name),
+        Err(error) => Err(error)?,
+        Ok(m) => {
+            DB::del(&m)?;
+            say!(ctx, msg, "Deleted `{}`.", name);
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description("Roll one of your macros, or one shared with the current channel.")]
+#[usage("<name> [arguments...]")]
+#[min_args(1)]
+fn run(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let name = args.single::<String>()?.to_lowercase();
+
+    match DB::find(&name, msg.author.id.into(), msg.channel_id.into()) {
+        Err(NotFound) => say!(ctx, msg, "Sorry, I don't know a macro called `{}`.", name),
+        Err(error) => Err(error)?,
+        Ok(m) => run_macro(ctx, msg, &m, args.rest()),
+    }
+
+    Ok(())
+}
+
+group!({
+    name: "macros",
+    options: { prefix: "macro" },
+    commands: [save, list, delete, run]
+});
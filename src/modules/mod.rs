@@ -2,6 +2,7 @@ pub mod admin;
 pub mod chartrack;
 pub mod dice;
 pub mod gurps;
+pub mod macros;
 pub mod memory;
 pub mod random;
 pub mod toys;
@@ -1,11 +1,11 @@
 // use chrono::{Date, Offset, Utc};
+use crate::ext::units;
 use crate::model::Owner;
 use humantime::format_duration;
 use serenity::model::id::MessageId;
 //use serenity::model::misc::Mentionable;
 use std::time::Duration;
 //use std::io::{Seek, SeekFrom, Write};
-use std::process::Command;
 use sysinfo::{get_current_pid, ProcessExt, System, SystemExt};
 use serenity::client::Context;
 use serenity::framework::standard::{Args, CommandResult};
@@ -57,20 +57,22 @@ fn about(ctx: &mut Context, msg: &Message) -> CommandResult {
 }
 
 #[command]
-#[description = "A unit-aware precision calculator based on GNU units."]
+#[description = "A unit-aware calculator for SI and US customary units."]
 #[min_args(1)]
-#[usage("expr[, into-unit]`\nFor details, see https://www.gnu.org/software/units/manual/units.html `\u{200B}")]
+#[usage("expr[, into-unit]`\nFor example, `5 ft + 3 in, cm` or `60 mph, m/s` `\u{200B}")]
 fn calc(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
-    let expr = args.message().split(',');
+    let mut expr = args.message().splitn(2, ',');
+    let from = expr.next().unwrap_or_default();
 
-    let output = Command::new("/usr/bin/units")
-        .env("UNITS_ENGLISH", "US")
-        .arg("--terse")
-        .arg("--")
-        .args(expr)
-        .output()?;
+    let result = match expr.next() {
+        Some(into) => units::convert(from, into).map(units::number),
+        None => units::parse(from).map(|q| q.to_string()),
+    };
 
-    msg.reply(&ctx, &String::from_utf8_lossy(&output.stdout))?;
+    match result {
+        Ok(result) => reply!(ctx, msg, "{}", result),
+        Err(err) => reply!(ctx, msg, "Sorry, {}.", err),
+    }
 
     Ok(())
 }